@@ -0,0 +1,272 @@
+// Headless-режим: repo2txt <path> [-o out.md] [--stdout] [--config cfg.json]
+// Тот же конвейер, что open_directory + generate_markdown, но без окна Tauri.
+use crate::engine::analysis::analyze_file;
+use crate::engine::patterns::{PathPatterns, PrivateRules};
+use crate::engine::secrets::SecretScanner;
+use crate::engine::split::{part_path, write_parts};
+use crate::engine::{
+    ContentTransform, Generator, NoopProgress, Scanner, Templates, TokenCache, Tokenizer,
+};
+use crate::models::{AppConfig, AppStats, FileClass};
+use futures::{stream, StreamExt};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const USAGE: &str = "Usage: repo2txt <path> [-o <file>] [--stdout] [--config <file>]
+
+Without arguments the desktop application is started.
+
+Options:
+  -o, --output <file>   Write the result to <file> (default: <path>/<output_filename>)
+      --stdout          Write the result to stdout
+  -c, --config <file>   Load AppConfig from a JSON file
+  -h, --help            Print this help
+  -V, --version         Print version";
+
+#[derive(Debug, Default)]
+struct CliArgs {
+    path: Option<String>,
+    output: Option<String>,
+    stdout: bool,
+    config: Option<String>,
+}
+
+enum ParsedArgs {
+    Run(CliArgs),
+    Help,
+    Version,
+}
+
+fn parse_args(args: Vec<String>) -> Result<ParsedArgs, String> {
+    let mut parsed = CliArgs::default();
+    let mut iter = args.into_iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(ParsedArgs::Help),
+            "-V" | "--version" => return Ok(ParsedArgs::Version),
+            "--stdout" => parsed.stdout = true,
+            "-o" | "--output" => {
+                parsed.output = Some(iter.next().ok_or("Missing value for --output")?);
+            }
+            "-c" | "--config" => {
+                parsed.config = Some(iter.next().ok_or("Missing value for --config")?);
+            }
+            _ if arg.starts_with('-') => return Err(format!("Unknown option: {}", arg)),
+            _ => {
+                if parsed.path.is_some() {
+                    return Err(format!("Unexpected argument: {}", arg));
+                }
+                parsed.path = Some(arg);
+            }
+        }
+    }
+
+    if parsed.stdout && parsed.output.is_some() {
+        return Err("--stdout and --output cannot be used together".to_string());
+    }
+
+    Ok(ParsedArgs::Run(parsed))
+}
+
+// Нужно ли запускать headless-режим вместо GUI.
+// macOS при запуске из Finder может передать аргумент вида -psn_0_12345, его игнорируем.
+pub fn should_run(args: &[String]) -> bool {
+    args.iter().any(|a| !a.starts_with("-psn_"))
+}
+
+// Точка входа CLI. Возвращает код завершения процесса.
+pub fn run(args: Vec<String>) -> i32 {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let args = match parse_args(args) {
+        Ok(ParsedArgs::Run(args)) => args,
+        Ok(ParsedArgs::Help) => {
+            println!("{}", USAGE);
+            return 0;
+        }
+        Ok(ParsedArgs::Version) => {
+            println!("repo2txt {}", env!("CARGO_PKG_VERSION"));
+            return 0;
+        }
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            return 2;
+        }
+    };
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(rt) => rt,
+        Err(e) => {
+            eprintln!("error: Failed to start runtime: {}", e);
+            return 1;
+        }
+    };
+
+    match runtime.block_on(execute(args)) {
+        Ok(stats) => {
+            eprintln!(
+                "Collected {} files ({} bytes, ~{} tokens)",
+                stats.files, stats.size, stats.tokens
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+fn load_app_config(path: Option<&str>) -> Result<AppConfig, String> {
+    match path {
+        Some(p) => {
+            let content = fs::read_to_string(p)
+                .map_err(|e| format!("Failed to read config {}: {}", p, e))?;
+            serde_json::from_str(&content).map_err(|e| format!("Failed to parse config {}: {}", p, e))
+        }
+        None => Ok(AppConfig::default()),
+    }
+}
+
+async fn execute(args: CliArgs) -> Result<AppStats, String> {
+    let app_config = load_app_config(args.config.as_deref())?;
+
+    let root = match args.path {
+        Some(p) => PathBuf::from(p),
+        None => std::env::current_dir()
+            .map_err(|e| format!("Failed to get current directory: {}", e))?,
+    };
+    if !root.is_dir() {
+        return Err(format!("Directory does not exist: {}", root.display()));
+    }
+    let root = root
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve {}: {}", root.display(), e))?;
+
+    // Куда пишем: --stdout, -o (относительно текущей директории) или output_filename в корне
+    let output_path = if args.stdout {
+        None
+    } else {
+        Some(match args.output {
+            Some(o) => std::env::current_dir()
+                .map_err(|e| format!("Failed to get current directory: {}", e))?
+                .join(o),
+            None => root.join(&app_config.output_filename),
+        })
+    };
+
+    // Части разбитого вывода - отдельные документы, в один поток их не склеить
+    if args.stdout && app_config.split_output && app_config.split_token_budget > 0 {
+        return Err(
+            "--stdout cannot be used with split output; use --output or set split_output to false".to_string(),
+        );
+    }
+
+    // Ошибка в настройках токенизатора (например, неверный путь) - не повод тихо считать иначе
+    let tokenizer = Tokenizer::from_config(&app_config)?;
+    // Как и опечатка в шаблоне или в шаблонах путей
+    Templates::compile(&app_config)?;
+    PathPatterns::compile(&root, &app_config)?;
+    PrivateRules::compile(&root, &app_config)?;
+    SecretScanner::from_config(&app_config)?;
+
+    // 1. Сканирование с учетом .gitignore, .r2x_ignore и сохраненного .r2x
+    let scanner = Scanner::new(&root, app_config.clone());
+    let saved_state = scanner.load_saved_state();
+    let mut selection = scanner.scan_with_state(saved_state.as_ref());
+
+    // Не включаем в результат сам выходной файл (и его части), если он лежит внутри проекта
+    if let Some(out) = &output_path {
+        if let Ok(relative) = out.strip_prefix(&root) {
+            selection.remove(relative.to_string_lossy().as_ref());
+            let mut index = 1;
+            while selection.remove(part_path(relative, index).to_string_lossy().as_ref()).is_some() {
+                index += 1;
+            }
+        }
+    }
+
+    // Повторный запуск пересчитывает токены только для измененных файлов
+    let transform = ContentTransform::from_config(&app_config);
+    let token_cache = Arc::new(TokenCache::open(&root, &tokenizer, &transform));
+
+    // Как и в приложении: сгенерированные файлы без сохраненного выбора пропускаются.
+    // Токены посчитанные здесь генерация возьмет из кэша.
+    if app_config.deselect_generated {
+        let mut generated: Vec<(String, FileClass)> = stream::iter(
+            selection
+                .selected_files()
+                .into_iter()
+                .filter(|n| saved_state.as_ref().is_none_or(|saved| !saved.contains_key(&n.id))),
+        )
+        .map(|node| {
+            let (tokenizer, transform, token_cache) = (&tokenizer, &transform, &token_cache);
+            async move {
+                let (_, counts) = analyze_file(&node.path, tokenizer, transform, token_cache).await;
+                counts.class.map(|class| (node.id, class))
+            }
+        })
+        .buffer_unordered(50)
+        .filter_map(std::future::ready)
+        .collect()
+        .await;
+        generated.sort_by(|a, b| a.0.cmp(&b.0));
+        for (id, class) in &generated {
+            let kind = match class {
+                FileClass::Generated => "generated",
+                FileClass::Minified => "minified",
+            };
+            eprintln!("Skipped {} file {}", kind, id);
+        }
+        let ids: Vec<String> = generated.into_iter().map(|(id, _)| id).collect();
+        selection.set_files_selected(&ids, false);
+    }
+
+    // 2. Чтение и форматирование выбранных файлов
+    let output = Generator::new(app_config)
+        .with_root(&root)
+        .with_tokenizer(tokenizer)
+        .with_token_cache(token_cache.clone())
+        .generate(&selection, &NoopProgress)
+        .await;
+    if let Err(e) = token_cache.save() {
+        log::warn!("Failed to save token cache: {}", e);
+    }
+    for skeleton in &output.skeletons {
+        eprintln!(
+            "Skeleton of {}: ~{} tokens instead of ~{}",
+            skeleton.path, skeleton.tokens, skeleton.full_tokens
+        );
+    }
+    for redaction in &output.redactions {
+        eprintln!("Redacted {} in {}:{}", redaction.kind, redaction.path, redaction.line);
+    }
+    let content = output.content;
+
+    match output_path {
+        Some(path) if !output.parts.is_empty() => {
+            for (written, part) in write_parts(&path, &output.parts)?.iter().zip(&output.parts) {
+                eprintln!("Part written to {} (~{} tokens)", written.display(), part.stats.tokens);
+            }
+        }
+        Some(path) => {
+            write_output(&path, &content)?;
+            eprintln!("Output written to {}", path.display());
+        }
+        None => {
+            let mut out = std::io::stdout().lock();
+            out.write_all(content.as_bytes())
+                .and_then(|_| out.flush())
+                .map_err(|e| format!("Failed to write to stdout: {}", e))?;
+        }
+    }
+
+    Ok(output.stats)
+}
+
+fn write_output(path: &Path, content: &str) -> Result<(), String> {
+    fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
@@ -1,3 +1,8 @@
+use crate::engine::autoselect::Priority;
+use crate::engine::encoding::decode;
+use crate::engine::patterns::{PathPatterns, PrivateRules};
+use crate::engine::secrets::SecretScanner;
+use crate::engine::Templates;
 use crate::models::{AppConfig, FileNode};
 use crate::models::ui::{get_app_settings_schema, SettingSection};
 use crate::state::AppState;
@@ -15,6 +20,8 @@ struct ConfigNode {
     is_directory: bool,
     selected: bool,
     expanded: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    skeleton: bool,
     children: Option<Vec<ConfigNode>>,
 }
 
@@ -37,6 +44,7 @@ fn file_node_to_config_node(node: &FileNode, nodes: &HashMap<String, FileNode>)
         is_directory: node.is_directory,
         selected: node.selected,
         expanded: node.expanded,
+        skeleton: node.skeleton,
         children: if children.is_empty() {
             None
         } else {
@@ -63,6 +71,9 @@ fn config_node_to_file_node(
         token_count: None,
         selected: config.selected,
         expanded: config.expanded,
+        skeleton: config.skeleton,
+        class: None,
+        encoding: None,
     }
 }
 
@@ -77,7 +88,7 @@ pub async fn read_file(id: String, state: State<'_, Arc<AppState>>) -> Result<St
         let nodes_map = state.nodes.lock().map_err(|_| "Failed to lock nodes")?;
         
         // Логируем для отладки, если файл не найден
-        if !nodes_map.contains_key(&id) {
+        if !nodes_map.contains(&id) {
             log::error!("File ID '{}' not found in nodes map. Total nodes: {}", id, nodes_map.len());
             return Err(format!("File not found in index: {}", id));
         }
@@ -111,7 +122,10 @@ pub async fn read_file(id: String, state: State<'_, Arc<AppState>>) -> Result<St
             format!("Failed to read file: {}", e)
         })?;
 
-        let content = String::from_utf8_lossy(&buffer[..bytes_read]);
+        let content = match decode(&buffer[..bytes_read]) {
+            Some((content, _)) => content,
+            None => return Ok(String::from("*Binary file*")),
+        };
         log::info!("Read {} bytes from file {} (truncated)", bytes_read, id);
         return Ok(format!(
             "{}\n\n--- TRUNCATED (File too large: {} bytes) ---",
@@ -121,19 +135,19 @@ pub async fn read_file(id: String, state: State<'_, Arc<AppState>>) -> Result<St
 
     log::info!("Reading file {} ({} bytes)", id, file_size);
 
-    // Читаем файл с безопасной обработкой UTF-8
+    // Читаем файл и перекодируем в UTF-8 (UTF-16, Windows-1251 и т.п.)
     let bytes = fs::read(Path::new(&file_path)).await.map_err(|e| {
         log::error!("Failed to read file {}: {}", file_path, e);
         format!("Failed to read file: {}", e)
     })?;
 
-    // Проверка на бинарность по содержимому (первые 1KB)
-    if bytes.iter().take(1024).any(|&b| b == 0) {
-        log::warn!("File {} detected as binary", file_path);
-        return Ok(String::from("*Binary file*"));
+    match decode(&bytes) {
+        Some((content, _)) => Ok(content.into_owned()),
+        None => {
+            log::warn!("File {} detected as binary", file_path);
+            Ok(String::from("*Binary file*"))
+        }
     }
-
-    Ok(String::from_utf8_lossy(&bytes).to_string())
 }
 
 #[tauri::command]
@@ -150,6 +164,7 @@ pub async fn save_config(state: State<'_, Arc<AppState>>) -> Result<(), String>
 
         // Получаем корневые узлы
         let root_nodes: Vec<&FileNode> = nodes_map
+            .nodes()
             .values()
             .filter(|n| {
                 n.parent_id.is_none() || n.parent_id.as_ref().map(|p| p.is_empty()).unwrap_or(true)
@@ -158,7 +173,7 @@ pub async fn save_config(state: State<'_, Arc<AppState>>) -> Result<(), String>
 
         let config_nodes: Vec<ConfigNode> = root_nodes
             .iter()
-            .map(|n| file_node_to_config_node(n, &nodes_map))
+            .map(|n| file_node_to_config_node(n, nodes_map.nodes()))
             .collect();
 
         (config_nodes, root)
@@ -260,7 +275,7 @@ pub async fn load_config(state: State<'_, Arc<AppState>>) -> Result<Vec<FileNode
     }
 
     for config_node in &config.nodes {
-        load_node(config_node, &root, None, &mut nodes_map, &mut result_nodes);
+        load_node(config_node, &root, None, nodes_map.nodes_mut(), &mut result_nodes);
     }
 
     Ok(result_nodes)
@@ -277,3 +292,20 @@ pub async fn get_default_config() -> Result<AppConfig, String> {
     log::debug!("Getting default config");
     Ok(AppConfig::default())
 }
+
+// Проверка настроек перед сохранением (шаблоны вывода и путей, выражения секретов, приоритеты автовыбора)
+#[tauri::command]
+pub async fn validate_config(config: AppConfig) -> Result<(), String> {
+    log::debug!("Validating config");
+    if let Some(name) = config.auto_select_priorities.iter().find(|name| Priority::parse(name).is_none()) {
+        return Err(format!(
+            "Unknown fit priority \"{}\". Expected one of: {}",
+            name,
+            Priority::NAMES.join(", ")
+        ));
+    }
+    PathPatterns::compile(Path::new(""), &config)?;
+    PrivateRules::compile(Path::new(""), &config)?;
+    SecretScanner::from_config(&config)?;
+    Templates::compile(&config).map(|_| ())
+}
@@ -0,0 +1,41 @@
+use crate::engine::git::{DiffTarget, GitRepo};
+use crate::state::AppState;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::State;
+
+// Выбирает только файлы, отличающиеся от base_ref; остальные снимаются с выбора.
+// Возвращает id выбранных файлов.
+#[tauri::command]
+pub async fn select_changed_files(
+    base_ref: String,
+    target: Option<DiffTarget>,
+    merge_base: Option<bool>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<String>, String> {
+    let root = state
+        .root_path
+        .lock()
+        .map_err(|_| "Failed to lock root_path")?
+        .clone()
+        .ok_or("No root path set")?;
+    let target = target.unwrap_or_default();
+    log::info!("Selecting files changed against '{}' ({:?})", base_ref, target);
+
+    let changed = tauri::async_runtime::spawn_blocking(move || {
+        let repo = GitRepo::open(&PathBuf::from(&root))?;
+        repo.changed_files(&base_ref, target, merge_base.unwrap_or(false))
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let mut selection = state.nodes.lock().map_err(|_| "Failed to lock nodes")?;
+    let selected = selection.select_only(&changed);
+
+    log::info!(
+        "Selected {} of {} changed files",
+        selected.len(),
+        changed.len()
+    );
+    Ok(selected)
+}
@@ -1,3 +1,63 @@
+use crate::engine::ProgressReporter;
+use crate::models::{FileUpdate, ProgressEvent};
+use crate::state::AppState;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter};
+
 pub mod file_ops;
 pub mod generator;
+pub mod git;
 pub mod repository;
+pub mod watcher;
+
+// События ядра пробрасываются во фронтенд как события Tauri
+impl ProgressReporter for AppHandle {
+    fn on_progress(&self, event: ProgressEvent) {
+        let _ = self.emit("generation-progress", event);
+    }
+
+    fn on_files_updated(&self, updates: &[FileUpdate]) {
+        if let Err(e) = self.emit("files-updated", updates) {
+            log::error!("Failed to emit update: {}", e);
+        }
+    }
+
+    fn on_analysis_completed(&self) {
+        let _ = self.emit("analysis-completed", ());
+    }
+}
+
+// Результаты фонового анализа сохраняются в AppState.nodes и уходят во фронтенд.
+// Сгенерированные файлы из `deselect` (новые, без сохраненного выбора) снимаются с выбора.
+pub(crate) struct StateReporter {
+    pub app_handle: AppHandle,
+    pub state: Arc<AppState>,
+    pub deselect: HashSet<String>,
+}
+
+impl ProgressReporter for StateReporter {
+    fn on_files_updated(&self, updates: &[FileUpdate]) {
+        let mut updates = updates.to_vec();
+        if let Ok(mut selection) = self.state.nodes.lock() {
+            for update in &mut updates {
+                if let Some(node) = selection.get_mut(&update.id) {
+                    node.size = Some(update.size);
+                    node.token_count = Some(update.token_count);
+                    node.class = update.class;
+                    node.encoding = update.encoding;
+                    if update.class.is_some() && node.selected && self.deselect.contains(&update.id) {
+                        log::debug!("Deselecting generated file: {}", update.id);
+                        node.selected = false;
+                        update.selected = Some(false);
+                    }
+                }
+            }
+        }
+        self.app_handle.on_files_updated(&updates);
+    }
+
+    fn on_analysis_completed(&self) {
+        self.app_handle.on_analysis_completed();
+    }
+}
@@ -0,0 +1,115 @@
+use super::StateReporter;
+use crate::engine::analysis::analyze_files;
+use crate::engine::watcher::apply_changes;
+use crate::engine::{RepoWatcher, Scanner};
+use crate::models::AppConfig;
+use crate::state::AppState;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, State};
+
+#[tauri::command]
+pub async fn start_watching(
+    config: Option<AppConfig>,
+    state: State<'_, Arc<AppState>>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let root = state
+        .root_path
+        .lock()
+        .map_err(|_| "Failed to lock root_path")?
+        .clone()
+        .ok_or("No root path set")?;
+    let scan_id = *state.current_scan_id.lock().map_err(|_| "Lock error")?;
+
+    // Используем переданный конфиг или дефолтный
+    let scanner = Scanner::new(&root, config.unwrap_or_default());
+    let tokenizer = state.current_tokenizer()?;
+    let transform = state.current_transform()?;
+    let token_cache = state.token_cache_for(Path::new(&root), &tokenizer, &transform)?;
+    let state_arc = state.inner().clone();
+
+    let watcher = RepoWatcher::start(&PathBuf::from(&root), move |paths| {
+        // Изменения от устаревшего сканирования игнорируем (открыт другой проект)
+        let current_scan_id = state_arc.current_scan_id.lock().map(|g| *g).unwrap_or(0);
+        if current_scan_id != scan_id {
+            return;
+        }
+
+        let delta = match state_arc.nodes.lock() {
+            Ok(mut selection) => apply_changes(&scanner, &mut selection, &paths),
+            Err(_) => {
+                log::error!("Failed to lock nodes for watcher update");
+                return;
+            }
+        };
+        if delta.is_empty() {
+            return;
+        }
+
+        log::info!(
+            "Tree changed: {} added, {} removed, {} modified",
+            delta.added.len(),
+            delta.removed.len(),
+            delta.modified.len()
+        );
+        if let Err(e) = app_handle.emit("tree-changed", &delta) {
+            log::error!("Failed to emit tree-changed: {}", e);
+        }
+
+        // Повторный анализ только для новых и измененных файлов
+        let items: Vec<(String, String)> = delta
+            .added
+            .iter()
+            .filter(|n| !n.is_directory)
+            .map(|n| (n.id.clone(), n.path.clone()))
+            .chain(delta.modified.iter().filter_map(|id| {
+                state_arc
+                    .nodes
+                    .lock()
+                    .ok()
+                    .and_then(|s| s.get(id).map(|n| (n.id.clone(), n.path.clone())))
+            }))
+            .collect();
+        if items.is_empty() {
+            return;
+        }
+
+        // Выбор снимается только с новых файлов: измененные пользователь уже видел
+        let deselect: HashSet<String> = match scanner.config().deselect_generated {
+            true => delta.added.iter().filter(|n| !n.is_directory).map(|n| n.id.clone()).collect(),
+            false => HashSet::new(),
+        };
+        let reporter = StateReporter {
+            app_handle: app_handle.clone(),
+            state: state_arc.clone(),
+            deselect,
+        };
+        let tokenizer = tokenizer.clone();
+        let transform = transform.clone();
+        let token_cache = token_cache.clone();
+        tauri::async_runtime::spawn(async move {
+            let is_cancelled =
+                || reporter.state.current_scan_id.lock().map(|g| *g).unwrap_or(0) != scan_id;
+            analyze_files(items, &tokenizer, &transform, &token_cache, &reporter, is_cancelled).await;
+            let saved = tauri::async_runtime::spawn_blocking(move || token_cache.save()).await;
+            if let Ok(Err(e)) = saved {
+                log::warn!("Failed to save token cache: {}", e);
+            }
+        });
+    })?;
+
+    // Новый наблюдатель заменяет предыдущий (старый останавливается при drop)
+    *state.watcher.lock().map_err(|_| "Lock error")? = Some(watcher);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn stop_watching(state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    let previous = state.watcher.lock().map_err(|_| "Lock error")?.take();
+    if previous.is_some() {
+        log::info!("File watcher stopped");
+    }
+    Ok(())
+}
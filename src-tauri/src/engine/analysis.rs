@@ -0,0 +1,150 @@
+use crate::engine::encoding::decode;
+use crate::engine::generated::classify;
+use crate::engine::language::get_language_by_extension;
+use crate::engine::progress::ProgressReporter;
+use crate::engine::token_cache::{TokenCache, TokenCounts};
+use crate::engine::tokens::Tokenizer;
+use crate::engine::transform::ContentTransform;
+use crate::models::{FileUpdate, TextEncoding};
+use futures::{stream, StreamExt};
+use std::path::Path;
+use tokio::fs;
+
+const NO_TOKENS: TokenCounts = TokenCounts {
+    tokens: 0,
+    raw_tokens: 0,
+    class: None,
+    encoding: None,
+};
+
+// Токены текста после преобразований и исходного (если текст не изменился, считаем один раз)
+// и класс файла по исходному тексту
+pub fn count_tokens(
+    tokenizer: &Tokenizer,
+    path: &str,
+    encoding: TextEncoding,
+    raw: &str,
+    transformed: &str,
+) -> TokenCounts {
+    let tokens = tokenizer.count(transformed);
+    let raw_tokens = if raw == transformed { tokens } else { tokenizer.count(raw) };
+    TokenCounts {
+        tokens,
+        raw_tokens,
+        class: classify(path, raw),
+        encoding: Some(encoding),
+    }
+}
+
+// Анализ одного файла: размер, количество токенов после `transform` и без него (бинарные файлы - 0 токенов),
+// класс (сгенерированный, минифицированный) и кодировка.
+// Неизмененные файлы берутся из кэша без чтения содержимого.
+pub async fn analyze_file(
+    path: &str,
+    tokenizer: &Tokenizer,
+    transform: &ContentTransform,
+    cache: &TokenCache,
+) -> (u64, TokenCounts) {
+    let path_obj = Path::new(path);
+
+    // 1. Получаем размер
+    let meta = match fs::metadata(path_obj).await {
+        Ok(m) => m,
+        Err(_) => return (0, NO_TOKENS),
+    };
+    let size = meta.len();
+
+    if let Some(counts) = cache.get(path, &meta) {
+        return (size, counts);
+    }
+
+    // 2. Считаем токены по тексту, перекодированному в UTF-8
+    let counts = match fs::read(path_obj).await {
+        Ok(bytes) => match decode(&bytes) {
+            Some((raw, encoding)) => {
+                let transformed = transform.apply(&raw, get_language_by_extension(path));
+                count_tokens(tokenizer, path, encoding, &raw, &transformed)
+            }
+            None => {
+                log::warn!("File {} detected as binary during read", path);
+                NO_TOKENS // Бинарные файлы не считаем токены
+            }
+        },
+        Err(_) => return (size, NO_TOKENS),
+    };
+    cache.insert(path, &meta, counts);
+
+    (size, counts)
+}
+
+// Фоновый анализ списка файлов (id, path) с батчингом результатов.
+// `is_cancelled` проверяется перед каждым файлом и батчем; возвращает false, если анализ прерван.
+pub async fn analyze_files<F>(
+    items: Vec<(String, String)>,
+    tokenizer: &Tokenizer,
+    transform: &ContentTransform,
+    cache: &TokenCache,
+    reporter: &dyn ProgressReporter,
+    is_cancelled: F,
+) -> bool
+where
+    F: Fn() -> bool + Send + Sync,
+{
+    // Батчинг обновлений, чтобы не спамить событиями (каждые 100 файлов)
+    let mut batch = Vec::with_capacity(100);
+
+    // Используем поток для параллельной обработки
+    let is_cancelled = &is_cancelled;
+    let mut stream = stream::iter(items)
+        .map(|(id, path)| async move {
+            // Проверяем, не отменили ли сканирование
+            if is_cancelled() {
+                log::debug!("Analysis cancelled, skipping file {}", id);
+                return None;
+            }
+
+            // Дополнительная проверка: пропускаем файлы из игнорируемых папок
+            // (на случай, если они все же попали в список)
+            if id.split('/').any(|component| component == ".git" || component == "node_modules") {
+                return None;
+            }
+
+            let (size, counts) = analyze_file(&path, tokenizer, transform, cache).await;
+
+            Some(FileUpdate {
+                id,
+                size,
+                token_count: counts.tokens,
+                class: counts.class,
+                encoding: counts.encoding,
+                selected: None,
+            })
+        })
+        .buffer_unordered(50); // 50 файлов параллельно
+
+    while let Some(update_opt) = stream.next().await {
+        // Проверяем, не отменили ли сканирование перед отправкой батча
+        if is_cancelled() {
+            return false;
+        }
+
+        if let Some(update) = update_opt {
+            batch.push(update);
+
+            if batch.len() >= 100 {
+                reporter.on_files_updated(&batch);
+                batch.clear();
+            }
+        }
+    }
+
+    // Отправляем остатки только если сканирование не отменено
+    if is_cancelled() {
+        return false;
+    }
+    if !batch.is_empty() {
+        reporter.on_files_updated(&batch);
+    }
+    reporter.on_analysis_completed();
+    true
+}
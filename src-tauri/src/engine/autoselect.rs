@@ -0,0 +1,263 @@
+use crate::engine::analysis::analyze_file;
+use crate::engine::token_cache::TokenCache;
+use crate::engine::tokens::Tokenizer;
+use crate::engine::transform::ContentTransform;
+use crate::models::{AppConfig, FileNode};
+use futures::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+use tokio::fs;
+
+// Порядок, в котором файлы занимают бюджет (после закрепленных)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Shallow, // Ближе к корню проекта
+    Small,   // Меньше токенов
+    Recent,  // Недавно измененные
+}
+
+impl Priority {
+    pub const NAMES: &'static [&'static str] = &["shallow", "small", "recent"];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "shallow" => Some(Self::Shallow),
+            "small" => Some(Self::Small),
+            "recent" => Some(Self::Recent),
+            _ => None,
+        }
+    }
+
+    fn compare(self, a: &Candidate, b: &Candidate) -> Ordering {
+        match self {
+            Self::Shallow => a.depth.cmp(&b.depth),
+            Self::Small => a.tokens.cmp(&b.tokens),
+            Self::Recent => b.modified.cmp(&a.modified),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AutoSelectOptions {
+    #[serde(default)]
+    pub budget: Option<usize>, // None - token_limit из конфига
+    #[serde(default)]
+    pub pinned: Vec<String>, // Файлы и папки, которые берутся первыми
+    #[serde(default)]
+    pub priorities: Vec<Priority>, // Сравниваются по очереди; без приоритетов - порядок путей
+    #[serde(default)]
+    pub exclude_tests: bool,
+}
+
+impl AutoSelectOptions {
+    // Параметры из настроек; неизвестные приоритеты пропускаются (validate_config их не пропустит)
+    pub fn from_config(config: &AppConfig) -> Self {
+        let priorities = config
+            .auto_select_priorities
+            .iter()
+            .filter_map(|name| {
+                let priority = Priority::parse(name);
+                if priority.is_none() {
+                    log::warn!("Unknown auto-select priority: {}", name);
+                }
+                priority
+            })
+            .collect();
+        Self {
+            budget: None,
+            pinned: config.auto_select_pinned.clone(),
+            priorities,
+            exclude_tests: config.auto_select_exclude_tests,
+        }
+    }
+
+    fn is_pinned(&self, path: &str) -> bool {
+        self.pinned.iter().any(|pin| {
+            let pin = pin.trim_end_matches('/');
+            path == pin || path.strip_prefix(pin).is_some_and(|rest| rest.starts_with('/'))
+        })
+    }
+}
+
+// Бюджет автовыбора. Оформление документа занимает его наравне с содержимым файлов:
+// заголовок резервируется сразу, обертка шаблона и путь добавляются к каждому файлу
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Budget {
+    pub tokens: usize,
+    pub header_tokens: usize, // Заголовок с деревом и завершение документа
+    pub file_overhead: usize, // Шаблон одного файла без пути и содержимого
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DropReason {
+    OverBudget,
+    TestFile,
+    TooLarge, // Больше max_file_size - генерация все равно пропустит содержимое
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DroppedFile {
+    pub path: String,
+    pub tokens: usize, // Вместе с оформлением файла
+    pub reason: DropReason,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AutoSelection {
+    pub selected: Vec<String>, // id выбранных файлов
+    pub tokens: usize,         // Оценка документа: заголовок и выбранные файлы с оформлением (без экранирования JSON/HTML)
+    pub budget: usize,
+    pub dropped: Vec<DroppedFile>,
+}
+
+struct Candidate {
+    node: FileNode,
+    tokens: usize,
+    size: u64,
+    depth: usize,
+    modified: u64,
+    pinned: bool,
+}
+
+// Тестовые файлы и каталоги по общепринятым именам
+pub fn is_test_path(path: &str) -> bool {
+    let mut components: Vec<&str> = path.split('/').collect();
+    let name = components.pop().unwrap_or("");
+    let test_dir = components
+        .iter()
+        .any(|c| matches!(*c, "test" | "tests" | "__tests__" | "spec" | "specs" | "testdata" | "__mocks__"));
+
+    test_dir
+        || name.contains(".test.")
+        || name.contains(".spec.")
+        || name.ends_with("_test.go")
+        || name.ends_with("_test.rs")
+        || name.ends_with("_test.py")
+        || name.ends_with("_spec.rb")
+        || (name.starts_with("test_") && name.ends_with(".py"))
+        || name == "tests.rs"
+        || name == "conftest.py"
+}
+
+// Подбор файлов под бюджет токенов: сначала закрепленные, затем по приоритетам.
+// Файл, который не помещается, пропускается, но следующие (меньшие) еще могут поместиться.
+pub async fn auto_select(
+    files: Vec<FileNode>,
+    options: &AutoSelectOptions,
+    budget: &Budget,
+    max_file_size: u64,
+    tokenizer: &Tokenizer,
+    transform: &ContentTransform,
+    cache: &TokenCache,
+) -> AutoSelection {
+    // Токены (из дерева или кэша) и дата изменения всех файлов параллельно
+    let mut candidates: Vec<Candidate> = stream::iter(files)
+        .map(|node| async move {
+            let meta = fs::metadata(&node.path).await.ok();
+            let modified = meta
+                .as_ref()
+                .and_then(|m| m.modified().ok())
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|since| since.as_secs())
+                .unwrap_or(0);
+            let size = meta.as_ref().map(|m| m.len()).unwrap_or(0);
+            let tokens = match node.token_count {
+                Some(tokens) => tokens,
+                None => analyze_file(&node.path, tokenizer, transform, cache).await.1.tokens,
+            };
+            Candidate {
+                depth: Path::new(&node.relative_path).components().count(),
+                pinned: options.is_pinned(&node.relative_path),
+                node,
+                tokens,
+                size,
+                modified,
+            }
+        })
+        .buffer_unordered(50)
+        .collect()
+        .await;
+
+    candidates.sort_by(|a, b| {
+        b.pinned
+            .cmp(&a.pinned)
+            .then_with(|| {
+                options
+                    .priorities
+                    .iter()
+                    .fold(Ordering::Equal, |order, p| order.then_with(|| p.compare(a, b)))
+            })
+            .then_with(|| a.node.relative_path.cmp(&b.node.relative_path))
+    });
+
+    let mut result = AutoSelection {
+        selected: Vec::new(),
+        tokens: budget.header_tokens,
+        budget: budget.tokens,
+        dropped: Vec::new(),
+    };
+    for candidate in candidates {
+        let cost = candidate.tokens + budget.file_overhead + tokenizer.count(&candidate.node.relative_path);
+        let reason = if candidate.size > max_file_size {
+            Some(DropReason::TooLarge)
+        } else if options.exclude_tests && !candidate.pinned && is_test_path(&candidate.node.relative_path) {
+            Some(DropReason::TestFile)
+        } else if result.tokens + cost > budget.tokens {
+            Some(DropReason::OverBudget)
+        } else {
+            None
+        };
+
+        match reason {
+            Some(reason) => result.dropped.push(DroppedFile {
+                path: candidate.node.relative_path,
+                tokens: cost,
+                reason,
+            }),
+            None => {
+                result.tokens += cost;
+                result.selected.push(candidate.node.id);
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_test_files_by_name() {
+        for path in [
+            "src/app.test.ts",
+            "src/app.spec.js",
+            "pkg/server_test.go",
+            "src/parser_test.rs",
+            "test_utils.py",
+            "lib/user_spec.rb",
+            "src/tests.rs",
+            "conftest.py",
+        ] {
+            assert!(is_test_path(path), "{}", path);
+        }
+    }
+
+    #[test]
+    fn detects_test_directories() {
+        assert!(is_test_path("tests/integration.rs"));
+        assert!(is_test_path("src/__tests__/App.tsx"));
+        assert!(is_test_path("pkg/testdata/input.json"));
+    }
+
+    #[test]
+    fn keeps_regular_files() {
+        for path in ["src/main.rs", "src/latest.rs", "src/testing.rs", "contest.py", "tests", "src/attest/mod.rs"] {
+            assert!(!is_test_path(path), "{}", path);
+        }
+    }
+}
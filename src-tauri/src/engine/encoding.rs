@@ -0,0 +1,245 @@
+use crate::models::TextEncoding;
+use std::borrow::Cow;
+
+// По стольким первым байтам определяются UTF-16 без BOM и бинарность
+const SAMPLE_SIZE: usize = 1024;
+
+// Чтение кодовой единицы UTF-16 (LE или BE)
+type UnitReader = fn([u8; 2]) -> u16;
+
+// Windows-1251, байты 0x80..=0xBF (0xC0..=0xFF - А..я подряд)
+const CP1251_HIGH: [u16; 64] = [
+    0x0402, 0x0403, 0x201A, 0x0453, 0x201E, 0x2026, 0x2020, 0x2021, 0x20AC, 0x2030, 0x0409, 0x2039,
+    0x040A, 0x040C, 0x040B, 0x040F, 0x0452, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014,
+    0x0098, 0x2122, 0x0459, 0x203A, 0x045A, 0x045C, 0x045B, 0x045F, 0x00A0, 0x040E, 0x045E, 0x0408,
+    0x00A4, 0x0490, 0x00A6, 0x00A7, 0x0401, 0x00A9, 0x0404, 0x00AB, 0x00AC, 0x00AD, 0x00AE, 0x0407,
+    0x00B0, 0x00B1, 0x0406, 0x0456, 0x0491, 0x00B5, 0x00B6, 0x00B7, 0x0451, 0x2116, 0x0454, 0x00BB,
+    0x0458, 0x0405, 0x0455, 0x0457,
+];
+
+// Windows-1252, байты 0x80..=0x9F (остальное совпадает с Latin-1)
+const CP1252_HIGH: [u16; 32] = [
+    0x20AC, 0x0081, 0x201A, 0x0192, 0x201E, 0x2026, 0x2020, 0x2021, 0x02C6, 0x2030, 0x0160, 0x2039,
+    0x0152, 0x008D, 0x017D, 0x008F, 0x0090, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014,
+    0x02DC, 0x2122, 0x0161, 0x203A, 0x0153, 0x009D, 0x017E, 0x0178,
+];
+
+// Кодировка текста: BOM, затем UTF-16 без BOM (нули через байт), UTF-8, однобайтовые.
+// None - бинарный файл (нулевые байты не в UTF-16).
+pub fn detect(bytes: &[u8]) -> Option<TextEncoding> {
+    // UTF-32 не поддерживается (его BOM начинается так же, как у UTF-16 LE)
+    if bytes.starts_with(&[0xFF, 0xFE, 0, 0]) || bytes.starts_with(&[0, 0, 0xFE, 0xFF]) {
+        return None;
+    }
+    if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return Some(TextEncoding::Utf8);
+    }
+    if bytes.starts_with(&[0xFF, 0xFE]) {
+        return Some(TextEncoding::Utf16Le);
+    }
+    if bytes.starts_with(&[0xFE, 0xFF]) {
+        return Some(TextEncoding::Utf16Be);
+    }
+
+    let sample = &bytes[..bytes.len().min(SAMPLE_SIZE)];
+    if let Some(encoding) = detect_utf16(sample) {
+        return Some(encoding);
+    }
+    if sample.contains(&0) {
+        return None;
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(_) => Some(TextEncoding::Utf8),
+        // Последний символ обрезан (чтение первых N байт) - это все еще UTF-8
+        Err(e) if e.error_len().is_none() => Some(TextEncoding::Utf8),
+        // Отдельные битые байты в UTF-8 файле заменяются на U+FFFD, а не перекодируют весь файл
+        Err(_) if mostly_utf8(bytes) => Some(TextEncoding::Utf8),
+        Err(_) if looks_cyrillic(bytes) => Some(TextEncoding::Windows1251),
+        Err(_) => Some(TextEncoding::Windows1252),
+    }
+}
+
+// Текст в UTF-8 и его исходная кодировка; None - бинарный файл
+pub fn decode(bytes: &[u8]) -> Option<(Cow<'_, str>, TextEncoding)> {
+    let encoding = detect(bytes)?;
+    let text = match encoding {
+        TextEncoding::Utf8 => {
+            String::from_utf8_lossy(bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(bytes))
+        }
+        TextEncoding::Utf16Le => Cow::Owned(decode_utf16(
+            bytes.strip_prefix(&[0xFF, 0xFE]).unwrap_or(bytes),
+            u16::from_le_bytes,
+        )),
+        TextEncoding::Utf16Be => Cow::Owned(decode_utf16(
+            bytes.strip_prefix(&[0xFE, 0xFF]).unwrap_or(bytes),
+            u16::from_be_bytes,
+        )),
+        TextEncoding::Windows1251 => Cow::Owned(bytes.iter().map(|&b| cp1251_char(b)).collect()),
+        TextEncoding::Windows1252 => Cow::Owned(bytes.iter().map(|&b| cp1252_char(b)).collect()),
+    };
+    Some((text, encoding))
+}
+
+// UTF-16 без BOM: заметная доля ASCII (в LE нулевой каждый второй байт, в BE - каждый первый)
+// и никаких управляющих символов, которые есть почти в любом бинарном файле
+fn detect_utf16(sample: &[u8]) -> Option<TextEncoding> {
+    if sample.len() < 4 {
+        return None;
+    }
+    let candidates: [(TextEncoding, UnitReader); 2] = [
+        (TextEncoding::Utf16Le, u16::from_le_bytes),
+        (TextEncoding::Utf16Be, u16::from_be_bytes),
+    ];
+    candidates
+        .into_iter()
+        .find(|(_, unit)| looks_like_utf16(sample, *unit))
+        .map(|(encoding, _)| encoding)
+}
+
+fn looks_like_utf16(sample: &[u8], unit: UnitReader) -> bool {
+    let mut ascii = 0;
+    let mut total = 0;
+    for pair in sample.chunks_exact(2) {
+        let u = unit([pair[0], pair[1]]);
+        if u < 0x20 && !matches!(u, 0x09 | 0x0A | 0x0C | 0x0D) {
+            return false;
+        }
+        if u < 0x7F {
+            ascii += 1;
+        }
+        total += 1;
+    }
+    ascii * 10 >= total * 4
+}
+
+// Обрезанный последний байт (чтение первых N байт) отбрасывается
+fn decode_utf16(bytes: &[u8], unit: UnitReader) -> String {
+    let units = bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+// Больше половины не-ASCII последовательностей - корректные символы UTF-8.
+// В однобайтовых кодировках такие последовательности встречаются лишь случайно.
+fn mostly_utf8(bytes: &[u8]) -> bool {
+    let (mut valid, mut invalid) = (0, 0);
+    for chunk in bytes.utf8_chunks() {
+        valid += chunk.valid().chars().filter(|c| !c.is_ascii()).count();
+        if !chunk.invalid().is_empty() {
+            invalid += 1;
+        }
+    }
+    valid > invalid
+}
+
+// Буквы кириллицы в Windows-1251 (0xC0..=0xFF, Ё/ё) идут словами подряд,
+// а буквы с диакритикой Latin-1 обычно окружены ASCII (café, straße)
+fn looks_cyrillic(bytes: &[u8]) -> bool {
+    let is_letter = |b: u8| b >= 0xC0 || b == 0xA8 || b == 0xB8;
+    let (mut letters, mut adjacent) = (0, 0);
+    for (i, &b) in bytes.iter().enumerate() {
+        if !is_letter(b) {
+            continue;
+        }
+        letters += 1;
+        let prev = i > 0 && is_letter(bytes[i - 1]);
+        let next = bytes.get(i + 1).is_some_and(|&n| is_letter(n));
+        if prev || next {
+            adjacent += 1;
+        }
+    }
+    letters > 0 && adjacent * 2 > letters
+}
+
+fn cp1251_char(b: u8) -> char {
+    match b {
+        0x00..=0x7F => b as char,
+        0x80..=0xBF => char::from_u32(CP1251_HIGH[(b - 0x80) as usize] as u32)
+            .unwrap_or(char::REPLACEMENT_CHARACTER),
+        _ => char::from_u32(0x0410 + (b - 0xC0) as u32).unwrap_or(char::REPLACEMENT_CHARACTER),
+    }
+}
+
+fn cp1252_char(b: u8) -> char {
+    match b {
+        0x80..=0x9F => char::from_u32(CP1252_HIGH[(b - 0x80) as usize] as u32)
+            .unwrap_or(char::REPLACEMENT_CHARACTER),
+        _ => b as char,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(text: &str, big_endian: bool) -> Vec<u8> {
+        text.encode_utf16()
+            .flat_map(|u| if big_endian { u.to_be_bytes() } else { u.to_le_bytes() })
+            .collect()
+    }
+
+    #[test]
+    fn strips_byte_order_marks() {
+        let (text, encoding) = decode(b"\xEF\xBB\xBFfn main() {}").unwrap();
+        assert_eq!((text.as_ref(), encoding), ("fn main() {}", TextEncoding::Utf8));
+
+        let mut le = vec![0xFF, 0xFE];
+        le.extend(utf16("Привет", false));
+        let (text, encoding) = decode(&le).unwrap();
+        assert_eq!((text.as_ref(), encoding), ("Привет", TextEncoding::Utf16Le));
+
+        let mut be = vec![0xFE, 0xFF];
+        be.extend(utf16("key=value", true));
+        assert_eq!(decode(&be).unwrap().1, TextEncoding::Utf16Be);
+
+        // UTF-32 не поддерживается
+        assert!(detect(&[0xFF, 0xFE, 0, 0, b'a', 0, 0, 0]).is_none());
+    }
+
+    #[test]
+    fn detects_utf16_without_bom() {
+        let rc = "LANGUAGE 9, 1\r\nIDS_TITLE \"Über\"\r\n";
+        let (le, be) = (utf16(rc, false), utf16(rc, true));
+        let (text, encoding) = decode(&le).unwrap();
+        assert_eq!((text.as_ref(), encoding), (rc, TextEncoding::Utf16Le));
+        let (text, encoding) = decode(&be).unwrap();
+        assert_eq!((text.as_ref(), encoding), (rc, TextEncoding::Utf16Be));
+    }
+
+    #[test]
+    fn treats_nul_bytes_outside_utf16_as_binary() {
+        assert!(detect(b"\x7FELF\x02\x01\x01\x00\x00\x00\x00\x00").is_none());
+        assert!(detect(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 0x0D]).is_none());
+    }
+
+    #[test]
+    fn distinguishes_cp1251_from_cp1252() {
+        // "Привет, мир" в Windows-1251
+        let cyrillic = b"\xCF\xF0\xE8\xE2\xE5\xF2, \xEC\xE8\xF0";
+        let (text, encoding) = decode(cyrillic).unwrap();
+        assert_eq!((text.as_ref(), encoding), ("Привет, мир", TextEncoding::Windows1251));
+
+        // "café – straße" в Windows-1252
+        let latin = b"caf\xE9 \x96 stra\xDFe";
+        let (text, encoding) = decode(latin).unwrap();
+        assert_eq!((text.as_ref(), encoding), ("café – straße", TextEncoding::Windows1252));
+    }
+
+    #[test]
+    fn keeps_utf8_with_truncated_tail() {
+        let bytes = "ёлка".as_bytes();
+        assert_eq!(detect(&bytes[..bytes.len() - 1]), Some(TextEncoding::Utf8));
+        assert_eq!(decode("ёлка".as_bytes()).unwrap().0, Cow::Borrowed("ёлка"));
+    }
+
+    #[test]
+    fn keeps_mostly_utf8_with_stray_byte() {
+        let mut bytes = "// Привет, мир\nlet s = \"ёлка\";\n".as_bytes().to_vec();
+        bytes.insert(3, 0xFF);
+        let (text, encoding) = decode(&bytes).unwrap();
+        assert_eq!(encoding, TextEncoding::Utf8);
+        assert_eq!(text, "// \u{FFFD}Привет, мир\nlet s = \"ёлка\";\n");
+    }
+}
@@ -0,0 +1,376 @@
+use serde::{Deserialize, Serialize};
+
+// Формат итогового документа (настройка `output_format`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Markdown, // Заголовок со структурой + файлы по output_template
+    Xml,      // <documents><document index="N">... (формат промптов Claude)
+    Json,     // Один объект: дерево + массив files
+    Jsonl,    // Одна запись на строку для каждого файла
+    Html,     // Автономная страница: дерево-навигация + раздел на каждый файл
+}
+
+// Содержимое файла в документе или причина, по которой оно пропущено
+pub enum FileBody {
+    Text(String),
+    TooLarge { limit: u64 },
+    Binary,
+    MetadataError,
+    ReadError,
+}
+
+impl FileBody {
+    pub fn skip_reason(&self) -> Option<&'static str> {
+        match self {
+            Self::Text(_) => None,
+            Self::TooLarge { .. } => Some("too large"),
+            Self::Binary => Some("binary"),
+            Self::MetadataError | Self::ReadError => Some("read error"),
+        }
+    }
+}
+
+// Раздел с git diff выбранных файлов
+pub struct ChangesSection {
+    pub base_ref: Option<String>,      // None - сравнение с индексом
+    pub diff: Result<String, String>, // Пустой diff - изменений нет
+}
+
+// Данные заголовка документа (или части большого вывода)
+pub struct DocumentHeader<'a> {
+    pub tree: Option<&'a str>, // None - вместо дерева список файлов части
+    pub files: &'a [&'a str],  // Файлы документа в порядке вывода
+    pub part: Option<(usize, usize)>,
+}
+
+// Ограда блока кода длиннее самой длинной серии обратных кавычек в содержимом (минимум три),
+// чтобы ``` внутри файла не закрывал блок раньше времени
+pub fn fence_for(content: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in content.chars() {
+        if c == '`' {
+            run += 1;
+            longest = longest.max(run);
+        } else {
+            run = 0;
+        }
+    }
+    "`".repeat((longest + 1).max(3))
+}
+
+// Символы, недопустимые в XML 1.0 даже внутри CDATA (управляющие символы, U+FFFE, U+FFFF)
+fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | '\u{20}'..='\u{FFFD}' | '\u{10000}'..)
+}
+
+// Экранирование текста для XML (содержимое элементов и атрибутов)
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c if !is_xml_char(c) => escaped.push(char::REPLACEMENT_CHARACTER),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Содержимое как CDATA; "]]>" внутри разбивается на две секции,
+// недопустимые символы заменяются на U+FFFD
+pub fn cdata(text: &str) -> String {
+    let text: String = text
+        .chars()
+        .map(|c| if is_xml_char(c) { c } else { char::REPLACEMENT_CHARACTER })
+        .collect();
+    format!("<![CDATA[{}]]>", text.replace("]]>", "]]]]><![CDATA[>"))
+}
+
+// Заголовок и завершение markdown задаются шаблонами (engine::template)
+pub mod markdown {
+    use super::{fence_for, ChangesSection};
+
+    // Строка оглавления части в шаблоне заголовка по умолчанию (для оценки размера)
+    pub fn index_line(path: &str) -> String {
+        format!("- {}\n", path)
+    }
+
+    pub fn changes(changes: &ChangesSection) -> String {
+        let against = changes
+            .base_ref
+            .as_ref()
+            .map(|r| format!("`{}`", r))
+            .unwrap_or_else(|| "the index".to_string());
+        match &changes.diff {
+            Ok(diff) if diff.is_empty() => {
+                format!("## Changes\n\n*No changes against {}*\n\n---\n\n", against)
+            }
+            Ok(diff) => {
+                let fence = fence_for(diff);
+                format!(
+                    "## Changes\n\nAgainst: {}\n\n{}diff\n{}{}\n\n---\n\n",
+                    against, fence, diff, fence
+                )
+            }
+            Err(e) => format!("## Changes\n\n*Could not compute diff: {}*\n\n---\n\n", e),
+        }
+    }
+}
+
+pub mod xml {
+    use super::{cdata, escape_xml, ChangesSection, DocumentHeader, FileBody};
+
+    pub fn header(header: &DocumentHeader) -> String {
+        let open = match header.part {
+            Some((index, total)) => format!("<documents part=\"{}\" total=\"{}\">\n", index, total),
+            None => "<documents>\n".to_string(),
+        };
+        match header.tree {
+            Some(tree) => format!("{}<file_tree>{}</file_tree>\n", open, cdata(tree)),
+            None => {
+                let index: String = header.files.iter().map(|p| index_line(p)).collect();
+                format!("{}<file_index>\n{}</file_index>\n", open, index)
+            }
+        }
+    }
+
+    pub fn index_line(path: &str) -> String {
+        format!("<source>{}</source>\n", escape_xml(path))
+    }
+
+    pub fn document(index: usize, source: &str, body: &FileBody) -> String {
+        let content = match body {
+            FileBody::Text(text) => format!("<document_content>{}</document_content>", cdata(text)),
+            other => format!(
+                "<skipped reason=\"{}\"/>",
+                other.skip_reason().unwrap_or_default()
+            ),
+        };
+        format!(
+            "<document index=\"{}\">\n<source>{}</source>\n{}\n</document>\n",
+            index,
+            escape_xml(source),
+            content
+        )
+    }
+
+    pub fn changes(changes: &ChangesSection) -> String {
+        let against = match &changes.base_ref {
+            Some(r) => format!(" against=\"{}\"", escape_xml(r)),
+            None => " against=\"index\"".to_string(),
+        };
+        match &changes.diff {
+            Ok(diff) => format!("<changes{}>{}</changes>\n", against, cdata(diff)),
+            Err(e) => format!("<changes{} error=\"{}\"/>\n", against, escape_xml(e)),
+        }
+    }
+
+    pub fn footer() -> &'static str {
+        "</documents>\n"
+    }
+}
+
+pub mod json {
+    use super::{ChangesSection, DocumentHeader};
+    use serde::Serialize;
+
+    // Запись о файле; для пропущенных файлов content = null, а skip_reason заполнен
+    #[derive(Serialize)]
+    pub struct FileRecord<'a> {
+        pub relative_path: &'a str,
+        pub language: &'a str,
+        pub size: u64,
+        pub tokens: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub lines: Option<(usize, usize)>, // Диапазон строк для кусков большого файла
+        pub content: Option<&'a str>,
+        pub skip_reason: Option<&'static str>,
+    }
+
+    #[derive(Serialize)]
+    struct ChangesRecord<'a> {
+        against: &'a str, // Ref или "index"
+        diff: Option<&'a str>,
+        error: Option<&'a str>,
+    }
+
+    fn to_string<T: Serialize + ?Sized>(value: &T) -> String {
+        serde_json::to_string(value).unwrap_or_else(|e| {
+            log::error!("Failed to serialize JSON output: {}", e);
+            "null".to_string()
+        })
+    }
+
+    // Начало объекта до открытого массива files; оглавление части не нужно - его заменяет сам массив
+    pub fn header(header: &DocumentHeader) -> String {
+        let mut out = String::from("{\n");
+        if let Some((index, total)) = header.part {
+            out.push_str(&format!("\"part\": {},\n\"total\": {},\n", index, total));
+        }
+        if let Some(tree) = header.tree {
+            out.push_str(&format!("\"tree\": {},\n", to_string(tree)));
+        }
+        out.push_str("\"files\": [\n");
+        out
+    }
+
+    pub fn record(record: &FileRecord) -> String {
+        to_string(record)
+    }
+
+    // Разделитель записей в массиве files
+    pub fn separator() -> &'static str {
+        ",\n"
+    }
+
+    pub fn changes(changes: &ChangesSection) -> String {
+        let (diff, error) = match &changes.diff {
+            Ok(diff) => (Some(diff.as_str()), None),
+            Err(e) => (None, Some(e.as_str())),
+        };
+        to_string(&ChangesRecord {
+            against: changes.base_ref.as_deref().unwrap_or("index"),
+            diff,
+            error,
+        })
+    }
+
+    // Закрытие массива files и объекта; раздел changes идет после файлов
+    pub fn closing(changes: Option<&str>) -> String {
+        match changes {
+            Some(changes) => format!("\n],\n\"changes\": {}\n}}\n", changes),
+            None => "\n]\n}\n".to_string(),
+        }
+    }
+}
+
+pub mod html {
+    use super::{escape_xml, ChangesSection, DocumentHeader, FileBody};
+
+    // Встроенные стили: файл должен открываться без сети
+    const STYLE: &str = "body{margin:0;display:flex;font:14px/1.5 system-ui,sans-serif;color:#1f2328}\
+nav{position:sticky;top:0;height:100vh;overflow:auto;min-width:260px;max-width:360px;padding:12px;\
+border-right:1px solid #d0d7de;background:#f6f8fa;box-sizing:border-box}\
+main{flex:1;min-width:0;padding:0 24px 24px}\
+nav ul{list-style:none;margin:0;padding-left:14px}nav>ul{padding-left:0}\
+summary{cursor:pointer}.excluded{color:#8c959f}.meta{color:#656d76;font-size:12px}\
+section{border-top:1px solid #d0d7de;margin-top:24px}h2{font-size:16px;font-family:monospace}\
+pre{background:#f6f8fa;padding:12px;overflow:auto;font-size:12px}a{color:#0969da;text-decoration:none}";
+
+    // Якорь раздела файла: символы вне [A-Za-z0-9._/-] кодируются как ~код~,
+    // чтобы разные пути не совпадали (сам `~` тоже кодируется)
+    pub fn anchor(path: &str) -> String {
+        let mut anchor = String::from("file-");
+        for c in path.chars() {
+            match c {
+                'A'..='Z' | 'a'..='z' | '0'..='9' | '.' | '_' | '/' | '-' => anchor.push(c),
+                c => anchor.push_str(&format!("~{:x}~", c as u32)),
+            }
+        }
+        anchor
+    }
+
+    // Начало страницы; `tree` - уже готовая HTML-разметка дерева
+    pub fn header(header: &DocumentHeader) -> String {
+        let title = match header.part {
+            Some((index, total)) => format!("Collected Files (part {} of {})", index, total),
+            None => "Collected Files".to_string(),
+        };
+        let nav = match header.tree {
+            Some(tree) => tree.to_string(),
+            None => {
+                let index: String = header.files.iter().map(|p| index_line(p)).collect();
+                format!("<ul>\n{}</ul>", index)
+            }
+        };
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+<style>{}</style>\n</head>\n<body>\n<nav>\n{}\n</nav>\n<main>\n<h1>{}</h1>\n",
+            title, STYLE, nav, title
+        )
+    }
+
+    pub fn index_line(path: &str) -> String {
+        format!("<li><a href=\"#{}\">{}</a></li>\n", anchor(path), escape_xml(path))
+    }
+
+    // Раздел файла с размером и токенами; пропущенные файлы - с причиной вместо содержимого
+    pub fn file(index: usize, anchor_path: &str, label: &str, language: &str, size: u64, tokens: usize, body: &FileBody) -> String {
+        let content = match body {
+            FileBody::Text(text) => format!(
+                "<pre><code class=\"language-{}\">{}</code></pre>",
+                escape_xml(language),
+                escape_xml(text)
+            ),
+            other => format!(
+                "<p class=\"excluded\">Skipped: {}</p>",
+                other.skip_reason().unwrap_or_default()
+            ),
+        };
+        format!(
+            "<section id=\"{}\">\n<h2>{}. {}</h2>\n<p class=\"meta\">{} &middot; {} bytes &middot; ~{} tokens</p>\n{}\n</section>\n",
+            anchor(anchor_path),
+            index,
+            escape_xml(label),
+            escape_xml(language),
+            size,
+            tokens,
+            content
+        )
+    }
+
+    pub fn changes(changes: &ChangesSection) -> String {
+        let against = changes
+            .base_ref
+            .as_ref()
+            .map(|r| format!("<code>{}</code>", escape_xml(r)))
+            .unwrap_or_else(|| "the index".to_string());
+        let content = match &changes.diff {
+            Ok(diff) if diff.is_empty() => format!("<p class=\"meta\">No changes against {}</p>", against),
+            Ok(diff) => format!(
+                "<p class=\"meta\">Against: {}</p>\n<pre><code class=\"language-diff\">{}</code></pre>",
+                against,
+                escape_xml(diff)
+            ),
+            Err(e) => format!("<p class=\"excluded\">Could not compute diff: {}</p>", escape_xml(e)),
+        };
+        format!("<section id=\"changes\">\n<h2>Changes</h2>\n{}\n</section>\n", content)
+    }
+
+    pub fn footer() -> &'static str {
+        "</main>\n</body>\n</html>\n"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fence_is_longer_than_any_backtick_run() {
+        assert_eq!(fence_for("plain text"), "```");
+        assert_eq!(fence_for("inline `code` and ``more``"), "```");
+        assert_eq!(fence_for("```rust\nfn main() {}\n```\n"), "````");
+        assert_eq!(fence_for("a ````` b ``` c"), "``````");
+    }
+
+    #[test]
+    fn html_anchor_keeps_dashes_and_encodes_the_rest() {
+        assert_eq!(html::anchor("src/my-app/main.rs"), "file-src/my-app/main.rs");
+        assert_eq!(html::anchor("a b~ж"), "file-a~20~b~7e~~436~");
+        assert_ne!(html::anchor("x y"), html::anchor("x-20-y"));
+    }
+
+    #[test]
+    fn escapes_xml_and_splits_cdata_end() {
+        assert_eq!(escape_xml("a<b & \"c\"\u{1}"), "a&lt;b &amp; &quot;c&quot;\u{FFFD}");
+        assert_eq!(cdata("x]]>y"), "<![CDATA[x]]]]><![CDATA[>y]]>");
+    }
+}
@@ -0,0 +1,146 @@
+use crate::models::FileClass;
+use regex::Regex;
+use std::path::Path;
+use std::sync::OnceLock;
+
+// Суффиксы минифицированных файлов и сборок
+const MINIFIED_SUFFIXES: &[&str] = &[".min.js", ".min.mjs", ".min.css", ".bundle.js", ".chunk.js"];
+// Суффиксы вывода кодогенераторов (protobuf, gRPC, build_runner, дизайнеры форм) и source maps
+const GENERATED_SUFFIXES: &[&str] = &[
+    ".pb.go",
+    ".pb.cc",
+    ".pb.h",
+    ".pb.swift",
+    "_pb2.py",
+    "_pb2.pyi",
+    "_pb2_grpc.py",
+    "_pb.js",
+    "_pb.d.ts",
+    ".g.dart",
+    ".freezed.dart",
+    ".g.cs",
+    ".designer.cs",
+    ".generated.ts",
+    ".generated.js",
+    ".generated.cs",
+    "_generated.go",
+    "_generated.rs",
+    ".gen.go",
+    ".gen.ts",
+    ".js.map",
+    ".css.map",
+];
+// Баннеры генераторов в заголовке. Баннер начинается с маркера комментария в начале строки,
+// а не упоминает генерацию посреди обычного комментария ("IDs are auto-generated by ..."):
+// `DO NOT EDIT` заглавными (Go, protoc, Thrift, C-генераторы), "generated by" сразу после
+// маркера, `@generated` (Meta, Rust-инструменты), тег `<auto-generated>` (.NET) на отдельной строке
+const HEADER_BANNERS: &[&str] = &[
+    r"^\s*(?://[/!]?|#+|/\*+|\*|--)\s*.*\bDO NOT EDIT\b",
+    r"^\s*(?://[/!]?|#+|/\*+|\*|--)\s*(?i:(?:this file (?:is|was|has been) )?(?:auto-?|automatically |machine[- ])?generated by)\b",
+    r"(?:^|[\s*#/(\[])@generated\b",
+    r"^\s*(?://+|#+|/?\*+)?\s*<auto-generated\b[^>]*>\s*(?:\*/)?$",
+];
+// Сколько первых строк считается заголовком
+const HEADER_LINES: usize = 10;
+
+// Минифицированным считается достаточно большой файл со средней длиной строки от порога
+const MINIFIED_MIN_SIZE: usize = 1024;
+const MINIFIED_AVG_LINE: usize = 200;
+// Текст и таблицы пишутся длинными строками, это не минификация
+const PROSE_EXTENSIONS: &[&str] = &["md", "markdown", "mdx", "txt", "rst", "adoc", "org", "tex", "csv", "tsv"];
+
+fn header_banners() -> &'static [Regex] {
+    static BANNERS: OnceLock<Vec<Regex>> = OnceLock::new();
+    BANNERS.get_or_init(|| {
+        HEADER_BANNERS
+            .iter()
+            .map(|pattern| Regex::new(pattern).expect("generated banner pattern"))
+            .collect()
+    })
+}
+
+// Класс файла по имени (суффиксу), метке в заголовке и средней длине строки
+pub fn classify(path: &str, content: &str) -> Option<FileClass> {
+    let name = Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if MINIFIED_SUFFIXES.iter().any(|s| name.ends_with(s)) {
+        return Some(FileClass::Minified);
+    }
+    if GENERATED_SUFFIXES.iter().any(|s| name.ends_with(s)) {
+        return Some(FileClass::Generated);
+    }
+
+    let has_banner = content
+        .lines()
+        .take(HEADER_LINES)
+        .any(|line| header_banners().iter().any(|banner| banner.is_match(line.trim_end())));
+    if has_banner {
+        return Some(FileClass::Generated);
+    }
+
+    let prose = name
+        .rsplit_once('.')
+        .is_some_and(|(_, ext)| PROSE_EXTENSIONS.contains(&ext));
+    if !prose && content.len() >= MINIFIED_MIN_SIZE {
+        let lines = content.lines().count().max(1);
+        if content.len() / lines >= MINIFIED_AVG_LINE {
+            return Some(FileClass::Minified);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_generator_banners() {
+        let go = "// Code generated by protoc-gen-go. DO NOT EDIT.\npackage api\n";
+        assert_eq!(classify("api/service.go", go), Some(FileClass::Generated));
+        let meta = "/**\n * @generated SignedSource<<abc>>\n */\nexport const x = 1;\n";
+        assert_eq!(classify("src/x.js", meta), Some(FileClass::Generated));
+        let dotnet = "//------\n// <auto-generated>\n//     This code was generated by a tool.\n// </auto-generated>\n";
+        assert_eq!(classify("Form1.cs", dotnet), Some(FileClass::Generated));
+    }
+
+    #[test]
+    fn detects_do_not_edit_and_generated_by_banners() {
+        let protoc = "# -*- coding: utf-8 -*-\n# Generated by the protocol buffer compiler.  DO NOT EDIT!\n";
+        assert_eq!(classify("api/service.py", protoc), Some(FileClass::Generated));
+        let c = "/* DO NOT EDIT THIS FILE - it is machine generated */\n#include <jni.h>\n";
+        assert_eq!(classify("native/Bridge.h", c), Some(FileClass::Generated));
+        let thrift = "/**\n * Autogenerated by Thrift Compiler (0.19.0)\n *\n * DO NOT EDIT UNLESS YOU ARE SURE THAT YOU KNOW WHAT YOU ARE DOING\n */\n";
+        assert_eq!(classify("gen/Service.java", thrift), Some(FileClass::Generated));
+        let sql = "-- Generated by sqlc v1.25.0\nSELECT 1;\n";
+        assert_eq!(classify("db/query.sql", sql), Some(FileClass::Generated));
+    }
+
+    #[test]
+    fn ignores_generation_mentioned_in_comments() {
+        let ids = "// IDs are auto-generated by the database\nstruct User { id: u64 }\n";
+        assert_eq!(classify("src/user.rs", ids), None);
+        let constant = "# do not edit this constant without updating the docs\nLIMIT = 10\n";
+        assert_eq!(classify("limits.py", constant), None);
+        let go = "// Code generated by hand, feel free to edit.\npackage api\n";
+        assert_eq!(classify("api.go", go), None);
+    }
+
+    #[test]
+    fn detects_suffixes_on_any_path() {
+        assert_eq!(classify("/repo/dist/app.min.js", ""), Some(FileClass::Minified));
+        assert_eq!(classify("/repo/api/service.pb.go", ""), Some(FileClass::Generated));
+        assert_eq!(classify("/repo/min.js/main.rs", "fn main() {}\n"), None);
+    }
+
+    #[test]
+    fn detects_long_lines_except_prose() {
+        let minified = "var a=1;".repeat(300);
+        assert_eq!(classify("bundle.js", &minified), Some(FileClass::Minified));
+        assert_eq!(classify("notes.md", &minified), None);
+        let code = "fn main() {}\n".repeat(200);
+        assert_eq!(classify("main.rs", &code), None);
+    }
+}
@@ -0,0 +1,1024 @@
+use crate::engine::analysis::{analyze_file, count_tokens};
+use crate::engine::encoding::decode;
+use crate::engine::format::{
+    escape_xml, fence_for, html, json, markdown, xml, ChangesSection, DocumentHeader, FileBody, OutputFormat,
+};
+use crate::engine::git::{format_date, GitFileInfo, GitRepo};
+use crate::engine::language::get_language_by_extension;
+use crate::engine::progress::ProgressReporter;
+use crate::engine::secrets::SecretScanner;
+use crate::engine::selection::Selection;
+use crate::engine::split::{pack, split_lines, OutputPart, PartItem};
+use crate::engine::template::Templates;
+use crate::engine::token_cache::TokenCache;
+use crate::engine::tokens::Tokenizer;
+use crate::engine::transform::ContentTransform;
+use crate::models::{AppConfig, AppStats, FileNode, ProgressEvent, Redaction, SkeletonSavings, TextEncoding};
+use futures::{stream, StreamExt};
+use minijinja::context;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::UNIX_EPOCH;
+use tokio::fs;
+
+// Путь в отчете о секретах для раздела "Changes"
+const CHANGES_LABEL: &str = "Changes (git diff)";
+
+// Дочерние узлы `parent_id` (None - корневые) в порядке вывода: папки сверху, затем по имени
+fn sorted_children<'a>(nodes: &'a HashMap<String, FileNode>, parent_id: Option<&str>) -> Vec<&'a FileNode> {
+    let mut children: Vec<&FileNode> = nodes
+        .values()
+        .filter(|n| n.parent_id.as_deref().filter(|p| !p.is_empty()) == parent_id)
+        .collect();
+    children.sort_by(|a, b| {
+        if a.is_directory != b.is_directory {
+            return b.is_directory.cmp(&a.is_directory); // Папки сверху
+        }
+        a.name.cmp(&b.name)
+    });
+    children
+}
+
+// Показываем дочерние элементы, если:
+// 1. Это директория И
+// 2. (Она развернута ИЛИ она выбрана - чтобы показать выбранные элементы даже в свернутых папках)
+fn shows_children(node: &FileNode) -> bool {
+    node.is_directory && (node.expanded || node.selected)
+}
+
+// Текстовое дерево структуры файлов для заголовка документа
+pub fn build_tree_structure(nodes: &HashMap<String, FileNode>) -> String {
+    let mut lines = Vec::new();
+
+    fn traverse(
+        node: &FileNode,
+        nodes: &HashMap<String, FileNode>,
+        prefix: &str,
+        is_last: bool,
+        lines: &mut Vec<String>,
+    ) {
+        let marker = if node.selected { "[✓]" } else { "[ ]" };
+        let icon = if node.is_directory { "▶ " } else { "" };
+        let current_prefix = if is_last { "└── " } else { "├── " };
+        let next_prefix = if is_last { "    " } else { "│   " };
+
+        lines.push(format!(
+            "{}{}{} {}{}",
+            prefix, current_prefix, marker, icon, node.name
+        ));
+
+        if shows_children(node) {
+            let children = sorted_children(nodes, Some(node.id.as_str()));
+            for (i, child) in children.iter().enumerate() {
+                let is_last_child = i == children.len() - 1;
+                traverse(
+                    child,
+                    nodes,
+                    &format!("{}{}", prefix, next_prefix),
+                    is_last_child,
+                    lines,
+                );
+            }
+        }
+    }
+
+    let sorted_roots = sorted_children(nodes, None);
+    for (i, node) in sorted_roots.iter().enumerate() {
+        let is_last = i == sorted_roots.len() - 1;
+        traverse(node, nodes, "", is_last, &mut lines);
+    }
+
+    lines.join("\n")
+}
+
+// То же дерево для HTML: папки - сворачиваемые <details>, включенные в документ файлы
+// (`included`, id узлов) - ссылки на свои разделы
+pub fn build_tree_html(nodes: &HashMap<String, FileNode>, included: &HashSet<String>) -> String {
+    fn traverse(
+        node: &FileNode,
+        nodes: &HashMap<String, FileNode>,
+        included: &HashSet<String>,
+        out: &mut String,
+    ) {
+        let name = escape_xml(&node.name);
+        if !node.is_directory {
+            if included.contains(&node.id) {
+                let tokens = node
+                    .token_count
+                    .map(|t| format!(" <span class=\"meta\">~{}</span>", t))
+                    .unwrap_or_default();
+                out.push_str(&format!(
+                    "<li><a href=\"#{}\">{}</a>{}</li>\n",
+                    html::anchor(&node.relative_path),
+                    name,
+                    tokens
+                ));
+            } else {
+                out.push_str(&format!("<li class=\"excluded\">{}</li>\n", name));
+            }
+            return;
+        }
+
+        let class = if node.selected { "" } else { " class=\"excluded\"" };
+        if !shows_children(node) {
+            out.push_str(&format!("<li{}>{}/</li>\n", class, name));
+            return;
+        }
+        let open = if node.expanded { " open" } else { "" };
+        out.push_str(&format!("<li{}><details{}><summary>{}/</summary>\n<ul>\n", class, open, name));
+        for child in sorted_children(nodes, Some(node.id.as_str())) {
+            traverse(child, nodes, included, out);
+        }
+        out.push_str("</ul>\n</details></li>\n");
+    }
+
+    let mut out = String::from("<ul>\n");
+    for node in sorted_children(nodes, None) {
+        traverse(node, nodes, included, &mut out);
+    }
+    out.push_str("</ul>");
+    out
+}
+
+// Обработанный файл (или кусок большого файла), готовый к выводу в любом формате
+pub struct ProcessedChunk {
+    pub relative_path: String,
+    pub label: String, // Путь для вывода: relative_path или "path (lines a-b)" для кусков
+    pub line_range: Option<(usize, usize)>, // Строки исходного файла для кусков
+    pub line_numbers: Option<Vec<usize>>, // Исходный номер каждой строки body, если преобразования меняли строки
+    pub continued: bool,                  // Кусок большого файла после первого
+    pub absolute_path: String,
+    pub body: FileBody,
+    pub original_size: u64,
+    pub token_count: usize,
+    pub full_token_count: Option<usize>, // Выведен скелет файла: токены полного текста
+    pub redactions: Vec<Redaction>,      // Секреты, замененные в содержимом
+    pub modified: String, // Дата изменения файла (YYYY-MM-DD, UTC); пусто, если неизвестна
+    pub index: usize,     // Номер файла в документе (с 1); у кусков - номер исходного файла
+    pub total: usize,     // Всего файлов в документе
+    pub git_info: Option<GitFileInfo>,
+}
+
+// Результат генерации: полный документ и статистика по включенным файлам.
+// При разбиении на части `parts` содержит части, а `content` - их конкатенацию.
+pub struct GenerateOutput {
+    pub content: String, // Документ; при разбиении - первая часть (склейка частей не валидна для JSON/XML)
+    pub stats: AppStats,
+    pub parts: Vec<OutputPart>,
+    pub skeletons: Vec<SkeletonSavings>, // Файлы, выведенные скелетом
+    pub redactions: Vec<Redaction>,      // Замененные секреты (файл и строка)
+}
+
+// Сборка итогового документа из выбранных файлов в формате из конфига
+pub struct Generator {
+    config: AppConfig,
+    root: Option<PathBuf>, // Корень проекта (нужен для git-разделов)
+    tokenizer: OnceLock<Arc<Tokenizer>>, // Загружается из конфига при первом обращении
+    token_cache: Option<Arc<TokenCache>>,
+    templates: OnceLock<Templates>, // Компилируются при первом обращении
+    transform: ContentTransform,
+    secrets: Option<SecretScanner>, // None - секреты не заменяются
+}
+
+impl Generator {
+    pub fn new(config: AppConfig) -> Self {
+        // Ошибка в пользовательском выражении не должна отключать встроенные детекторы
+        let secrets = SecretScanner::from_config(&config).unwrap_or_else(|e| {
+            log::warn!("{}", e);
+            Some(SecretScanner::builtin(config.secret_entropy_threshold))
+        });
+        Self {
+            transform: ContentTransform::from_config(&config),
+            secrets,
+            config,
+            root: None,
+            tokenizer: OnceLock::new(),
+            token_cache: None,
+            templates: OnceLock::new(),
+        }
+    }
+
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    // Уже загруженный токенизатор (тот же, что считал токены в дереве)
+    pub fn with_tokenizer(self, tokenizer: Arc<Tokenizer>) -> Self {
+        let _ = self.tokenizer.set(tokenizer);
+        self
+    }
+
+    // Кэш токенов для файлов, которые фоновый анализ еще не посчитал
+    pub fn with_token_cache(mut self, cache: Arc<TokenCache>) -> Self {
+        self.token_cache = Some(cache);
+        self
+    }
+
+    pub fn tokenizer(&self) -> &Tokenizer {
+        self.tokenizer
+            .get_or_init(|| Tokenizer::from_config_or_fallback(&self.config))
+    }
+
+    pub fn config(&self) -> &AppConfig {
+        &self.config
+    }
+
+    fn templates(&self) -> &Templates {
+        self.templates
+            .get_or_init(|| Templates::from_config_or_default(&self.config))
+    }
+
+    // Переменные шаблонов заголовка и завершения
+    fn document_context(header: &DocumentHeader) -> minijinja::Value {
+        context! {
+            tree => header.tree,
+            files => header.files,
+            part => header.part.map(|(index, total)| context! { index => index, total => total }),
+        }
+    }
+
+    // Заголовок документа (или части) со структурой файлов
+    pub fn render_header(&self, header: &DocumentHeader) -> String {
+        match self.config.output_format {
+            OutputFormat::Markdown => self
+                .templates()
+                .render_header(&Self::document_context(header))
+                .unwrap_or_else(|e| {
+                    log::warn!("Failed to render header template: {}", e);
+                    format!("*Template error: {}*\n\n", e)
+                }),
+            OutputFormat::Xml => xml::header(header),
+            OutputFormat::Json => json::header(header),
+            OutputFormat::Jsonl => String::new(),
+            OutputFormat::Html => html::header(header),
+        }
+    }
+
+    // Завершение markdown-документа по шаблону (остальные форматы закрываются в assemble)
+    fn render_footer(&self, header: &DocumentHeader) -> String {
+        if self.config.output_format != OutputFormat::Markdown {
+            return String::new();
+        }
+        self.templates()
+            .render_footer(&Self::document_context(header))
+            .unwrap_or_else(|e| {
+                log::warn!("Failed to render footer template: {}", e);
+                format!("*Template error: {}*\n", e)
+            })
+    }
+
+    // Строка оглавления части для файла
+    fn render_index_line(&self, path: &str) -> String {
+        match self.config.output_format {
+            OutputFormat::Markdown => markdown::index_line(path),
+            OutputFormat::Xml => xml::index_line(path),
+            OutputFormat::Json | OutputFormat::Jsonl => String::new(),
+            OutputFormat::Html => html::index_line(path),
+        }
+    }
+
+    // Один файл документа (куски большого файла - под номером исходного файла)
+    pub fn render_entry(&self, chunk: &ProcessedChunk) -> String {
+        match self.config.output_format {
+            OutputFormat::Markdown => match &chunk.body {
+                FileBody::Text(content) => self.render_file(chunk, content),
+                FileBody::Binary => self.render_file(chunk, "*Binary file*"),
+                FileBody::ReadError => self.render_file(chunk, "*Error reading file*"),
+                FileBody::MetadataError => format!(
+                    "## {}\n\n*Error: Could not read file*\n\n---\n\n",
+                    chunk.label
+                ),
+                FileBody::TooLarge { limit } => format!(
+                    "## {}\n\n*File too large ({} bytes, limit: {} bytes) - skipped*\n\n---\n\n",
+                    chunk.label, chunk.original_size, limit
+                ),
+            },
+            OutputFormat::Xml => xml::document(chunk.index, &chunk.label, &chunk.body),
+            OutputFormat::Json => json::record(&self.file_record(chunk)),
+            OutputFormat::Jsonl => format!("{}\n", json::record(&self.file_record(chunk))),
+            OutputFormat::Html => {
+                // Первый кусок большого файла доступен по якорю из дерева
+                let anchor_path = match chunk.continued {
+                    true => &chunk.label,
+                    false => &chunk.relative_path,
+                };
+                html::file(
+                    chunk.index,
+                    anchor_path,
+                    &chunk.label,
+                    get_language_by_extension(&chunk.relative_path),
+                    chunk.original_size,
+                    chunk.token_count,
+                    &chunk.body,
+                )
+            }
+        }
+    }
+
+    // Запись о файле для JSON/JSONL
+    fn file_record<'a>(&self, chunk: &'a ProcessedChunk) -> json::FileRecord<'a> {
+        let content = match &chunk.body {
+            FileBody::Text(content) => Some(content.as_str()),
+            _ => None,
+        };
+        json::FileRecord {
+            relative_path: &chunk.relative_path,
+            language: get_language_by_extension(&chunk.relative_path),
+            size: chunk.original_size,
+            tokens: chunk.token_count,
+            lines: chunk.line_range,
+            content,
+            skip_reason: chunk.body.skip_reason(),
+        }
+    }
+
+    pub fn render_changes(&self, changes: &ChangesSection) -> String {
+        match self.config.output_format {
+            OutputFormat::Markdown => markdown::changes(changes),
+            OutputFormat::Xml => xml::changes(changes),
+            OutputFormat::Json => json::changes(changes),
+            OutputFormat::Jsonl => String::new(),
+            OutputFormat::Html => html::changes(changes),
+        }
+    }
+
+    // Разделитель между записями файлов
+    fn entry_separator(&self) -> &'static str {
+        match self.config.output_format {
+            OutputFormat::Json => json::separator(),
+            _ => "",
+        }
+    }
+
+    // Документ (или часть) из заголовка, отрендеренных файлов и раздела Changes
+    fn assemble(&self, header: &DocumentHeader, entries: &[&str], changes: Option<&str>) -> String {
+        let mut content = self.render_header(header);
+        content.push_str(&entries.join(self.entry_separator()));
+        match self.config.output_format {
+            OutputFormat::Markdown => {
+                content.push_str(changes.unwrap_or_default());
+                content.push_str(&self.render_footer(header));
+            }
+            OutputFormat::Jsonl => content.push_str(changes.unwrap_or_default()),
+            OutputFormat::Xml => {
+                content.push_str(changes.unwrap_or_default());
+                content.push_str(xml::footer());
+            }
+            OutputFormat::Html => {
+                content.push_str(changes.unwrap_or_default());
+                content.push_str(html::footer());
+            }
+            OutputFormat::Json => content.push_str(&json::closing(changes)),
+        }
+        content
+    }
+
+    // Дерево файлов для заголовка; в HTML включенные файлы (`included`, id узлов) - ссылки
+    fn tree_structure(&self, nodes: &HashMap<String, FileNode>, included: &HashSet<String>) -> String {
+        match self.config.output_format {
+            OutputFormat::Html => build_tree_html(nodes, included),
+            _ => build_tree_structure(nodes),
+        }
+    }
+
+    // Заголовок с деревом и завершение документа без файлов - для оценки бюджета до генерации.
+    // В HTML все файлы считаются включенными: ссылки длиннее простых имен
+    pub fn header_tokens(&self, selection: &Selection) -> usize {
+        let nodes = selection.nodes();
+        let files: HashSet<String> = nodes.values().filter(|n| !n.is_directory).map(|n| n.id.clone()).collect();
+        let tree = self.tree_structure(nodes, &files);
+        let header = DocumentHeader {
+            tree: Some(&tree),
+            files: &[],
+            part: None,
+        };
+        self.tokenizer().count(&self.assemble(&header, &[], None))
+    }
+
+    // Оформление одного файла: шаблон с пустыми путем и содержимым и разделитель записей
+    pub fn file_overhead_tokens(&self) -> usize {
+        let empty = ProcessedChunk {
+            relative_path: String::new(),
+            label: String::new(),
+            line_range: None,
+            line_numbers: None,
+            continued: false,
+            absolute_path: String::new(),
+            body: FileBody::Text(String::new()),
+            original_size: 0,
+            token_count: 0,
+            full_token_count: None,
+            redactions: Vec::new(),
+            modified: String::new(),
+            index: 1,
+            total: 1,
+            git_info: None,
+        };
+        let tokenizer = self.tokenizer();
+        tokenizer.count(&self.render_entry(&empty)) + tokenizer.count(self.entry_separator())
+    }
+
+    // Unified diff выбранных файлов для раздела "Changes" (None, если выключен)
+    // JSONL содержит только записи о файлах, поэтому diff для него не строится.
+    pub async fn load_changes(&self, files: HashSet<String>) -> Option<ChangesSection> {
+        if !self.config.include_git_diff || self.config.output_format == OutputFormat::Jsonl {
+            return None;
+        }
+        let root = self.root.clone()?;
+
+        let base_ref = Some(self.config.git_diff_base.trim().to_string()).filter(|r| !r.is_empty());
+        let diff_ref = base_ref.clone();
+        let context_lines = self.config.git_diff_context;
+
+        // git2 работает синхронно - выносим из async-контекста
+        let diff = tokio::task::spawn_blocking(move || {
+            let repo = GitRepo::open(&root)?;
+            repo.unified_diff(diff_ref.as_deref(), &files, context_lines)
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r);
+
+        if let Err(e) = &diff {
+            log::warn!("Failed to compute git diff: {}", e);
+        }
+        Some(ChangesSection { base_ref, diff })
+    }
+
+    // Используются ли в шаблоне переменные git_* (история читается только тогда)
+    fn uses_git_vars(&self) -> bool {
+        self.config.output_format == OutputFormat::Markdown
+            && ["git_commit", "git_author", "git_date", "git_subject", "git_commits"]
+                .iter()
+                .any(|var| self.templates().file_uses(var))
+    }
+
+    // Git-метаданные выбранных файлов; пустая карта, если шаблон их не использует или это не git-репозиторий
+    pub async fn load_git_info(&self, files: HashSet<String>) -> HashMap<String, GitFileInfo> {
+        if !self.uses_git_vars() {
+            return HashMap::new();
+        }
+        let root = match &self.root {
+            Some(root) => root.clone(),
+            None => return HashMap::new(),
+        };
+
+        let history = tokio::task::spawn_blocking(move || {
+            let repo = GitRepo::open(&root)?;
+            repo.file_history(&files)
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r);
+
+        history.unwrap_or_else(|e| {
+            log::warn!("Failed to read git history: {}", e);
+            HashMap::new()
+        })
+    }
+
+    // Токены файла: из кэша, если файл не менялся, иначе подсчет с записью в кэш
+    fn count_tokens(
+        &self,
+        path: &str,
+        meta: &std::fs::Metadata,
+        encoding: TextEncoding,
+        raw: &str,
+        content: &str,
+    ) -> usize {
+        let cache = match &self.token_cache {
+            Some(cache) => cache,
+            None => return self.tokenizer().count(content),
+        };
+        let counts = cache.get(path, meta).unwrap_or_else(|| {
+            let counts = count_tokens(self.tokenizer(), path, encoding, raw, content);
+            cache.insert(path, meta, counts);
+            counts
+        });
+        counts.tokens
+    }
+
+    // Файл по шаблону из конфига (markdown). В path подставляется label,
+    // язык определяется по relative_path.
+    pub fn render_file(&self, chunk: &ProcessedChunk, content: &str) -> String {
+        // Git-метаданные (пустые значения для файлов вне истории)
+        let info = chunk.git_info.clone().unwrap_or_default();
+        let path = Path::new(&chunk.relative_path);
+        let lines = match &chunk.body {
+            FileBody::Text(text) => text.lines().count(),
+            _ => 0,
+        };
+        let context = context! {
+            path => &chunk.label,
+            name => path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default(),
+            extension => path.extension().map(|e| e.to_string_lossy()).unwrap_or_default(),
+            absolute_path => &chunk.absolute_path,
+            language => get_language_by_extension(&chunk.relative_path),
+            size => chunk.original_size,
+            tokens => chunk.token_count,
+            lines => lines,
+            modified => &chunk.modified,
+            index => chunk.index,
+            total => chunk.total,
+            fence => fence_for(content),
+            content => content,
+            git_commit => info.commit,
+            git_author => info.author,
+            git_date => info.date,
+            git_subject => info.subject,
+            git_commits => info.commits,
+        };
+
+        self.templates().render_file(&context).unwrap_or_else(|e| {
+            log::warn!("Failed to render template for {}: {}", chunk.label, e);
+            format!("## {}\n\n*Template error: {}*\n\n---\n\n", chunk.label, e)
+        })
+    }
+
+    // Чтение одного файла (с проверками размера и бинарности)
+    pub async fn process_file(&self, node: FileNode, git_info: Option<&GitFileInfo>) -> ProcessedChunk {
+        let config = &self.config;
+        let full_path = Path::new(&node.path);
+
+        // Проверяем размер файла перед чтением
+        let meta = fs::metadata(full_path).await;
+        let modified = meta
+            .as_ref()
+            .ok()
+            .and_then(|meta| meta.modified().ok())
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|since| format_date(since.as_secs() as i64, 0))
+            .unwrap_or_default();
+        let chunk = |body: FileBody, original_size: u64, token_count: usize| ProcessedChunk {
+            label: node.relative_path.clone(),
+            relative_path: node.relative_path.clone(),
+            line_range: None,
+            line_numbers: None,
+            continued: false,
+            absolute_path: node.path.clone(),
+            body,
+            original_size,
+            token_count,
+            full_token_count: None,
+            redactions: Vec::new(),
+            modified: modified.clone(),
+            index: 0, // Проставляется после сортировки
+            total: 0,
+            git_info: git_info.cloned(),
+        };
+
+        let meta = match meta {
+            Ok(meta) => meta,
+            Err(_) => {
+                log::warn!("Failed to get metadata for {}", node.path);
+                return chunk(FileBody::MetadataError, 0, 0);
+            }
+        };
+        let file_size = meta.len();
+
+        // Пропускаем файлы, превышающие лимит
+        if file_size > config.max_file_size {
+            log::warn!(
+                "File {} exceeds max_file_size ({} > {}), skipping",
+                node.path,
+                file_size,
+                config.max_file_size
+            );
+            return chunk(
+                FileBody::TooLarge {
+                    limit: config.max_file_size,
+                },
+                file_size,
+                0,
+            );
+        }
+
+        // Читаем файл и перекодируем в UTF-8 (UTF-16, Windows-1251 и т.п.)
+        match fs::read(full_path).await {
+            Ok(bytes) => match decode(&bytes) {
+                None => {
+                    log::warn!("File {} detected as binary during generation", node.path);
+                    chunk(FileBody::Binary, file_size, 0)
+                }
+                Some((text, encoding)) => {
+                    // Секреты заменяются до всех преобразований: скелет и нумерация строк
+                    // работают уже с замененным текстом
+                    let (raw, secrets) = match &self.secrets {
+                        Some(scanner) => scanner.redact(&text),
+                        None => (std::borrow::Cow::Borrowed(text.as_ref()), Vec::new()),
+                    };
+                    let redactions: Vec<Redaction> = secrets
+                        .into_iter()
+                        .map(|secret| Redaction {
+                            path: node.relative_path.clone(),
+                            line: secret.line,
+                            kind: secret.kind,
+                        })
+                        .collect();
+
+                    // Удаление комментариев, нумерация строк и т.п. - до подсчета токенов
+                    let language = get_language_by_extension(&node.path);
+                    // Токены из кэша, только если размер и mtime файла не изменились с подсчета:
+                    // token_count узла мог устареть, если файл изменился после анализа.
+                    // Кэш посчитан по файлу без замен, поэтому для файлов с секретами считаем заново.
+                    let full_tokens = |content: &str| match redactions.is_empty() {
+                        true => self.count_tokens(&node.path, &meta, encoding, &raw, content),
+                        false => self.tokenizer().count(content),
+                    };
+
+                    let skeleton = match config.skeleton_mode || node.skeleton {
+                        true => self.transform.apply_skeleton(&raw, language),
+                        false => None,
+                    };
+                    let mut processed = match skeleton {
+                        Some((skeleton, line_numbers)) => {
+                            let full = full_tokens(&self.transform.apply(&raw, language));
+                            let tokens = self.tokenizer().count(&skeleton);
+                            let mut processed = chunk(FileBody::Text(skeleton), file_size, tokens);
+                            processed.full_token_count = Some(full);
+                            processed.line_numbers = Some(line_numbers);
+                            processed
+                        }
+                        None => {
+                            let (content, line_numbers) = self.transform.apply_mapped(&raw, language);
+                            let content = content.into_owned();
+                            let tokens = full_tokens(&content);
+                            let mut processed = chunk(FileBody::Text(content), file_size, tokens);
+                            processed.line_numbers = line_numbers;
+                            processed
+                        }
+                    };
+                    processed.redactions = redactions;
+                    processed
+                }
+            },
+            Err(_) => chunk(FileBody::ReadError, file_size, 0),
+        }
+    }
+
+    // Бюджет токенов на часть, если включено разбиение вывода
+    fn split_budget(&self) -> Option<usize> {
+        Some(self.config.split_token_budget).filter(|b| self.config.split_output && *b > 0)
+    }
+
+    // Раскладка файлов и раздела Changes по частям не больше `budget` токенов.
+    // Каждая часть повторяет дерево файлов, а если оно занимает больше половины бюджета -
+    // короткое оглавление с файлами этой части.
+    fn split_output(
+        &self,
+        chunks: Vec<ProcessedChunk>,
+        changes: Option<String>,
+        tree_structure: &str,
+        budget: usize,
+    ) -> Vec<OutputPart> {
+        let tokenizer = self.tokenizer();
+        let empty_part = |tree: Option<&str>| {
+            let header = DocumentHeader {
+                tree,
+                files: &[],
+                part: Some((0, 0)),
+            };
+            tokenizer.count(&self.assemble(&header, &[], None))
+        };
+        let tree_tokens = empty_part(Some(tree_structure));
+        let use_tree = tree_tokens <= budget / 2;
+        let base_tokens = if use_tree {
+            tree_tokens
+        } else {
+            empty_part(None)
+        };
+        // Строка оглавления и разделитель записей на каждый файл
+        let separator_tokens = tokenizer.count(self.entry_separator());
+        let index_tokens = |label: &str| {
+            separator_tokens
+                + if use_tree {
+                    0
+                } else {
+                    tokenizer.count(&self.render_index_line(label))
+                }
+        };
+
+        let mut items = Vec::with_capacity(chunks.len() + 1);
+        for chunk in chunks {
+            let rendered = self.render_entry(&chunk);
+            let tokens = tokenizer.count(&rendered);
+            let available = budget
+                .saturating_sub(base_tokens)
+                .saturating_sub(index_tokens(&chunk.label));
+
+            // Файл больше части режется по строкам
+            if tokens > available {
+                let pieces = self.split_chunk(&chunk, available);
+                if pieces.len() > 1 {
+                    items.extend(pieces.into_iter().map(|mut item| {
+                        item.tokens += item.label.as_deref().map(index_tokens).unwrap_or(0);
+                        item
+                    }));
+                    continue;
+                }
+            }
+
+            items.push(PartItem {
+                tokens: tokens + index_tokens(&chunk.label),
+                label: Some(chunk.label),
+                content: rendered,
+                size: chunk.original_size,
+            });
+        }
+
+        if let Some(changes) = changes {
+            items.push(PartItem {
+                label: None,
+                tokens: tokenizer.count(&changes),
+                content: changes,
+                size: 0,
+            });
+        }
+
+        let groups = pack(items, budget, base_tokens);
+        let total = groups.len();
+        groups
+            .into_iter()
+            .enumerate()
+            .map(|(i, items)| {
+                let labels: Vec<&str> = items.iter().filter_map(|item| item.label.as_deref()).collect();
+                let header = DocumentHeader {
+                    tree: Some(tree_structure).filter(|_| use_tree),
+                    files: &labels,
+                    part: Some((i + 1, total)),
+                };
+                let entries: Vec<&str> = items
+                    .iter()
+                    .filter(|item| item.label.is_some())
+                    .map(|item| item.content.as_str())
+                    .collect();
+                let changes = items
+                    .iter()
+                    .find(|item| item.label.is_none())
+                    .map(|item| item.content.as_str());
+                let content = self.assemble(&header, &entries, changes);
+                let stats = AppStats {
+                    files: labels.len(),
+                    size: items.iter().map(|item| item.size).sum(),
+                    tokens: tokenizer.count(&content),
+                    raw_tokens: None,
+                };
+                OutputPart { content, stats }
+            })
+            .collect()
+    }
+
+    // Куски большого текстового файла по `max_tokens` (с учетом оформления).
+    // Диапазоны строк в подписях - по исходному файлу, даже если преобразования убрали строки
+    fn split_chunk(&self, chunk: &ProcessedChunk, max_tokens: usize) -> Vec<PartItem> {
+        let content = match &chunk.body {
+            FileBody::Text(content) => content,
+            _ => return Vec::new(),
+        };
+        let tokenizer = self.tokenizer();
+        let original_line = |line: usize| match &chunk.line_numbers {
+            Some(numbers) => numbers.get(line - 1).copied().unwrap_or(line),
+            None => line,
+        };
+        let piece = |first: usize, last: usize, text: String, continued: bool| ProcessedChunk {
+            relative_path: chunk.relative_path.clone(),
+            label: format!("{} (lines {}-{})", chunk.relative_path, first, last),
+            line_range: Some((first, last)),
+            line_numbers: None,
+            continued,
+            absolute_path: chunk.absolute_path.clone(),
+            original_size: text.len() as u64,
+            token_count: tokenizer.count(&text),
+            full_token_count: None,
+            redactions: Vec::new(), // Отчет о секретах - по исходному файлу
+            body: FileBody::Text(text),
+            modified: chunk.modified.clone(),
+            index: chunk.index,
+            total: chunk.total,
+            git_info: chunk.git_info.clone(),
+        };
+
+        // Место, которое занимает само оформление вокруг содержимого
+        let sample = piece(10000, 10000, String::new(), true);
+        let overhead = tokenizer.count(&self.render_entry(&sample));
+
+        let mut limit = max_tokens.saturating_sub(overhead);
+        loop {
+            let items: Vec<PartItem> = split_lines(content, limit, tokenizer)
+                .into_iter()
+                .enumerate()
+                .map(|(i, (first, last, text))| {
+                    let piece = piece(original_line(first), original_line(last), text, i > 0);
+                    let rendered = self.render_entry(&piece);
+                    PartItem {
+                        tokens: tokenizer.count(&rendered),
+                        content: rendered,
+                        size: piece.original_size,
+                        label: Some(piece.label),
+                    }
+                })
+                .collect();
+
+            // Экранирование (JSON) раздувает содержимое - уменьшаем лимит и режем заново
+            let excess = items
+                .iter()
+                .map(|item| item.tokens.saturating_sub(max_tokens))
+                .max()
+                .unwrap_or(0);
+            if excess == 0 || limit <= excess {
+                return items;
+            }
+            limit -= excess;
+        }
+    }
+
+    // Полная генерация: заголовок + выбранные файлы в детерминированном порядке
+    pub async fn generate(
+        &self,
+        selection: &Selection,
+        reporter: &dyn ProgressReporter,
+    ) -> GenerateOutput {
+        let selected_files = selection.selected_files();
+        let selected_ids: HashSet<String> = selected_files.iter().map(|n| n.id.clone()).collect();
+        let tree_structure = self.tree_structure(selection.nodes(), &selected_ids);
+        let git_info = self.load_git_info(selected_ids.clone()).await;
+        let git_info = &git_info;
+
+        let total_files = selected_files.len();
+        log::info!("Starting generation for {} files", total_files);
+
+        // Отправляем начальное событие прогресса
+        reporter.on_progress(ProgressEvent {
+            current: 0,
+            total: total_files,
+            stage: "preparing".to_string(),
+        });
+
+        // Параллельное чтение файлов
+        let progress_counter = AtomicUsize::new(0);
+        let progress_counter = &progress_counter;
+        let mut chunks = stream::iter(selected_files)
+            .map(|node| async move {
+                let info = git_info.get(&node.id);
+                let chunk = self.process_file(node, info).await;
+
+                // Обновляем прогресс каждые 5 файлов или при завершении
+                let current = progress_counter.fetch_add(1, Ordering::Relaxed) + 1;
+                if current.is_multiple_of(5) || current == total_files {
+                    reporter.on_progress(ProgressEvent {
+                        current,
+                        total: total_files,
+                        stage: "processing".to_string(),
+                    });
+                }
+
+                chunk
+            })
+            .buffer_unordered(50) // Читаем по 50 файлов параллельно
+            .collect::<Vec<_>>()
+            .await;
+
+        // Сортируем, чтобы порядок файлов в документе был детерминированным
+        chunks.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+        let total = chunks.len();
+        for (i, chunk) in chunks.iter_mut().enumerate() {
+            chunk.index = i + 1;
+            chunk.total = total;
+        }
+
+        // Отправляем событие начала записи
+        reporter.on_progress(ProgressEvent {
+            current: total_files,
+            total: total_files,
+            stage: "writing".to_string(),
+        });
+
+        let mut stats = AppStats {
+            files: total_files,
+            size: 0,
+            tokens: 0,
+            raw_tokens: None,
+        };
+        let mut skeletons = Vec::new();
+        let mut redactions = Vec::new();
+        for chunk in &chunks {
+            redactions.extend(chunk.redactions.iter().cloned());
+            stats.size += chunk.original_size;
+            stats.tokens += chunk.token_count;
+            if let Some(full_tokens) = chunk.full_token_count {
+                skeletons.push(SkeletonSavings {
+                    path: chunk.relative_path.clone(),
+                    tokens: chunk.token_count,
+                    full_tokens,
+                });
+            }
+        }
+
+        let changes = self.load_changes(selected_ids).await.map(|mut changes| {
+            // Секреты в diff (в том числе удаленные строки) заменяются так же, как в файлах
+            if let (Some(scanner), Ok(diff)) = (&self.secrets, &mut changes.diff) {
+                let (redacted, secrets) = scanner.redact(diff);
+                if !secrets.is_empty() {
+                    *diff = redacted.into_owned();
+                    redactions.extend(secrets.into_iter().map(|secret| Redaction {
+                        path: CHANGES_LABEL.to_string(),
+                        line: secret.line,
+                        kind: secret.kind,
+                    }));
+                }
+            }
+            self.render_changes(&changes)
+        });
+        if let Some(changes) = &changes {
+            stats.tokens += self.tokenizer().count(changes);
+        }
+
+        let (content, parts) = match self.split_budget() {
+            Some(budget) => {
+                let parts = self.split_output(chunks, changes, &tree_structure, budget);
+                log::info!("Output split into {} parts", parts.len());
+                (parts.first().map(|p| p.content.clone()).unwrap_or_default(), parts)
+            }
+            None => {
+                let entries: Vec<String> = chunks.iter().map(|chunk| self.render_entry(chunk)).collect();
+                let entries: Vec<&str> = entries.iter().map(String::as_str).collect();
+                let labels: Vec<&str> = chunks.iter().map(|chunk| chunk.label.as_str()).collect();
+                let header = DocumentHeader {
+                    tree: Some(&tree_structure),
+                    files: &labels,
+                    part: None,
+                };
+                (self.assemble(&header, &entries, changes.as_deref()), Vec::new())
+            }
+        };
+
+        reporter.on_progress(ProgressEvent {
+            current: total_files,
+            total: total_files,
+            stage: "completed".to_string(),
+        });
+
+        GenerateOutput {
+            content,
+            stats,
+            parts,
+            skeletons,
+            redactions,
+        }
+    }
+}
+
+// Статистика по выбранным файлам; токены считаются только там, где их еще нет.
+// При включенных преобразованиях содержимого в узлах нет исходных токенов -
+// тогда обе величины берутся из кэша токенов (или считаются заново).
+pub async fn compute_stats(
+    files: Vec<FileNode>,
+    tokenizer: &Tokenizer,
+    transform: &ContentTransform,
+    cache: &TokenCache,
+) -> AppStats {
+    let identity = transform.is_identity();
+    // Разделяем на кэшированные и те, что нужно подсчитать
+    let (cached, needs_calc): (Vec<_>, Vec<_>) =
+        files.into_iter().partition(|n| identity && n.token_count.is_some());
+
+    let mut total_stats = AppStats {
+        files: 0,
+        size: 0,
+        tokens: 0,
+        raw_tokens: None,
+    };
+    let mut raw_tokens = 0;
+
+    // Суммируем кэшированные
+    for node in cached {
+        total_stats.files += 1;
+        total_stats.size += node.size.unwrap_or(0);
+        total_stats.tokens += node.token_count.unwrap_or(0);
+        raw_tokens += node.token_count.unwrap_or(0);
+    }
+
+    // Считаем токены параллельно только для тех, где их нет
+    let calculated_results = stream::iter(needs_calc)
+        .map(|node| async move {
+            let (size, counts) = analyze_file(&node.path, tokenizer, transform, cache).await;
+            (node.size.unwrap_or(size), counts)
+        })
+        .buffer_unordered(50) // Параллельная обработка
+        .collect::<Vec<_>>()
+        .await;
+
+    // Обновляем статистику
+    for (size, counts) in calculated_results {
+        total_stats.files += 1;
+        total_stats.size += size;
+        total_stats.tokens += counts.tokens;
+        raw_tokens += counts.raw_tokens;
+    }
+
+    total_stats.raw_tokens = Some(raw_tokens);
+    total_stats
+}
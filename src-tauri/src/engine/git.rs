@@ -0,0 +1,331 @@
+use git2::{Commit, DiffOptions, Patch, Repository, Sort, Tree};
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// С чем сравнивается ref: рабочая копия (включая неотслеживаемые файлы) или HEAD
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffTarget {
+    #[default]
+    WorkingTree,
+    Head,
+}
+
+// Git-метаданные файла для переменных шаблона {{git_*}}
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GitFileInfo {
+    pub commit: String,  // Короткий хэш последнего коммита
+    pub author: String,  // Автор последнего коммита
+    pub date: String,    // Дата последнего коммита (YYYY-MM-DD)
+    pub subject: String, // Первая строка сообщения последнего коммита
+    pub commits: usize,  // Количество коммитов, затрагивающих файл
+}
+
+// Дата из секунд Unix и смещения часового пояса (в минутах) в формате YYYY-MM-DD
+pub fn format_date(seconds: i64, offset_minutes: i32) -> String {
+    let days = (seconds + offset_minutes as i64 * 60).div_euclid(86_400);
+
+    // Алгоритм civil_from_days (Howard Hinnant)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// Доступ к локальному git-репозиторию, в котором лежит корень проекта.
+// Работает только с .git на диске, без вызова git и без сети.
+pub struct GitRepo {
+    repo: Repository,
+    root: PathBuf,
+    workdir: PathBuf, // Канонический путь, как и root (иначе strip_prefix не сработает)
+}
+
+impl GitRepo {
+    // Ищет репозиторий вверх от root (проект может быть подпапкой репозитория)
+    pub fn open(root: &Path) -> Result<Self, String> {
+        let repo = Repository::discover(root)
+            .map_err(|e| format!("Not a git repository ({}): {}", root.display(), e.message()))?;
+        let workdir = match repo.workdir() {
+            Some(dir) => dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf()),
+            None => return Err("Bare repositories are not supported".to_string()),
+        };
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        Ok(Self { repo, root, workdir })
+    }
+
+    // Путь из git (относительно workdir) -> relative_path узла (относительно root).
+    // None для файлов вне root.
+    pub fn to_relative_path(&self, git_path: &Path) -> Option<String> {
+        self.workdir
+            .join(git_path)
+            .strip_prefix(&self.root)
+            .ok()
+            .map(|p| p.to_string_lossy().to_string())
+            .filter(|p| !p.is_empty())
+    }
+
+    // Дерево коммита по ref/хэшу; с merge_base - от общего предка ref и HEAD (как `git diff ref...`)
+    pub fn resolve_tree(&self, base_ref: &str, merge_base: bool) -> Result<Tree<'_>, String> {
+        let object = self
+            .repo
+            .revparse_single(base_ref)
+            .map_err(|e| format!("Unknown ref '{}': {}", base_ref, e.message()))?;
+        let commit = object
+            .peel_to_commit()
+            .map_err(|e| format!("'{}' is not a commit: {}", base_ref, e.message()))?;
+
+        let commit = if merge_base {
+            let head = self.head_commit()?;
+            let base_oid = self
+                .repo
+                .merge_base(commit.id(), head.id())
+                .map_err(|e| format!("No merge base with '{}': {}", base_ref, e.message()))?;
+            self.repo
+                .find_commit(base_oid)
+                .map_err(|e| format!("Failed to load merge base: {}", e.message()))?
+        } else {
+            commit
+        };
+
+        commit
+            .tree()
+            .map_err(|e| format!("Failed to load tree for '{}': {}", base_ref, e.message()))
+    }
+
+    fn head_commit(&self) -> Result<git2::Commit<'_>, String> {
+        self.repo
+            .head()
+            .and_then(|h| h.peel_to_commit())
+            .map_err(|e| format!("Failed to resolve HEAD: {}", e.message()))
+    }
+
+    // Diff между деревом base_ref и целью
+    pub fn diff(
+        &self,
+        base_ref: &str,
+        target: DiffTarget,
+        merge_base: bool,
+        opts: &mut DiffOptions,
+    ) -> Result<git2::Diff<'_>, String> {
+        let base_tree = self.resolve_tree(base_ref, merge_base)?;
+
+        match target {
+            DiffTarget::WorkingTree => {
+                opts.include_untracked(true).recurse_untracked_dirs(true);
+                self.repo
+                    .diff_tree_to_workdir_with_index(Some(&base_tree), Some(opts))
+            }
+            DiffTarget::Head => {
+                let head_tree = self
+                    .head_commit()?
+                    .tree()
+                    .map_err(|e| format!("Failed to load HEAD tree: {}", e.message()))?;
+                self.repo
+                    .diff_tree_to_tree(Some(&base_tree), Some(&head_tree), Some(opts))
+            }
+        }
+        .map_err(|e| format!("Failed to compute diff: {}", e.message()))
+    }
+
+    // Файлы (relative_path относительно root), отличающиеся от base_ref. Удаленные файлы не включаются.
+    pub fn changed_files(
+        &self,
+        base_ref: &str,
+        target: DiffTarget,
+        merge_base: bool,
+    ) -> Result<Vec<String>, String> {
+        let diff = self.diff(base_ref, target, merge_base, &mut DiffOptions::new())?;
+
+        let mut files: Vec<String> = diff
+            .deltas()
+            .filter(|d| d.status() != git2::Delta::Deleted)
+            .filter_map(|d| d.new_file().path().and_then(|p| self.to_relative_path(p)))
+            .collect();
+        files.sort();
+        files.dedup();
+        Ok(files)
+    }
+
+    // Unified diff выбранных файлов (relative_path) относительно base_ref или индекса (None).
+    // Пустая строка, если изменений нет.
+    pub fn unified_diff(
+        &self,
+        base_ref: Option<&str>,
+        files: &HashSet<String>,
+        context_lines: u32,
+    ) -> Result<String, String> {
+        let mut opts = DiffOptions::new();
+        opts.context_lines(context_lines)
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .show_untracked_content(true);
+
+        let diff = match base_ref {
+            Some(base_ref) => self.diff(base_ref, DiffTarget::WorkingTree, false, &mut opts)?,
+            None => self
+                .repo
+                .diff_index_to_workdir(None, Some(&mut opts))
+                .map_err(|e| format!("Failed to compute diff: {}", e.message()))?,
+        };
+
+        let mut output = String::new();
+        for idx in 0..diff.deltas().len() {
+            let delta = match diff.get_delta(idx) {
+                Some(d) => d,
+                None => continue,
+            };
+            let path = delta.new_file().path().or_else(|| delta.old_file().path());
+            let in_selection = path
+                .and_then(|p| self.to_relative_path(p))
+                .map(|p| files.contains(&p))
+                .unwrap_or(false);
+            if !in_selection {
+                continue;
+            }
+
+            if let Ok(Some(mut patch)) = Patch::from_diff(&diff, idx) {
+                let buf = patch
+                    .to_buf()
+                    .map_err(|e| format!("Failed to format diff: {}", e.message()))?;
+                output.push_str(&String::from_utf8_lossy(&buf));
+            }
+        }
+
+        Ok(output)
+    }
+
+    // Последний коммит и число коммитов для каждого файла (relative_path) за один проход по истории HEAD.
+    // Файлы без истории (неотслеживаемые) в результат не попадают.
+    pub fn file_history(&self, files: &HashSet<String>) -> Result<HashMap<String, GitFileInfo>, String> {
+        let mut result: HashMap<String, GitFileInfo> = HashMap::new();
+        if files.is_empty() {
+            return Ok(result);
+        }
+
+        let mut revwalk = self
+            .repo
+            .revwalk()
+            .map_err(|e| format!("Failed to walk history: {}", e.message()))?;
+        revwalk
+            .set_sorting(Sort::TOPOLOGICAL | Sort::TIME)
+            .and_then(|_| revwalk.push_head())
+            .map_err(|e| format!("Failed to resolve HEAD: {}", e.message()))?;
+
+        for oid in revwalk {
+            let commit = match oid.and_then(|oid| self.repo.find_commit(oid)) {
+                Ok(c) => c,
+                Err(e) => {
+                    log::warn!("Skipping unreadable commit: {}", e.message());
+                    continue;
+                }
+            };
+            let tree = match commit.tree() {
+                Ok(t) => t,
+                Err(_) => continue,
+            };
+
+            for relative_path in self.files_changed_by(&commit, &tree, files) {
+                let info = result.entry(relative_path).or_default();
+                if info.commits == 0 {
+                    // Обход идет от новых к старым - первый найденный коммит и есть последний
+                    let author = commit.author();
+                    info.commit = commit.id().to_string().chars().take(7).collect();
+                    info.author = author.name().unwrap_or("").to_string();
+                    info.date = format_date(author.when().seconds(), author.when().offset_minutes());
+                    info.subject = commit.summary().unwrap_or("").to_string();
+                }
+                info.commits += 1;
+            }
+        }
+
+        Ok(result)
+    }
+
+    // Файлы из `files`, измененные коммитом. Корневой коммит сравнивается с пустым деревом.
+    // Merge-коммит меняет файл, только если тот отличается от всех родителей (как `git log -- <file>`):
+    // изменения, пришедшие из влитой ветки, уже посчитаны в ее коммитах.
+    fn files_changed_by(&self, commit: &Commit<'_>, tree: &Tree<'_>, files: &HashSet<String>) -> HashSet<String> {
+        let parent_trees: Vec<Option<Tree<'_>>> = match commit.parent_count() {
+            0 => vec![None],
+            _ => commit.parents().map(|p| p.tree().ok()).collect(),
+        };
+
+        let mut changed: Option<HashSet<String>> = None;
+        for parent_tree in &parent_trees {
+            let diff = match self.repo.diff_tree_to_tree(parent_tree.as_ref(), Some(tree), None) {
+                Ok(d) => d,
+                Err(_) => return HashSet::new(),
+            };
+            let paths: HashSet<String> = diff
+                .deltas()
+                .filter_map(|delta| delta.new_file().path().and_then(|p| self.to_relative_path(p)))
+                .filter(|p| files.contains(p))
+                .collect();
+            let remaining = match changed {
+                Some(previous) => previous.intersection(&paths).cloned().collect(),
+                None => paths,
+            };
+            if remaining.is_empty() {
+                return remaining;
+            }
+            changed = Some(remaining);
+        }
+        changed.unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Oid, Signature};
+    use std::fs;
+
+    // Коммит текущего содержимого workdir с заданными родителями
+    fn commit(repo: &Repository, message: &str, parents: &[Oid]) -> Oid {
+        let mut index = repo.index().unwrap();
+        index.add_all(["*"], git2::IndexAddOption::DEFAULT, None).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        let parents: Vec<Commit> = parents.iter().map(|id| repo.find_commit(*id).unwrap()).collect();
+        let parents: Vec<&Commit> = parents.iter().collect();
+        repo.commit(None, &signature, &signature, message, &tree, &parents).unwrap()
+    }
+
+    #[test]
+    fn merge_commits_do_not_count_merged_changes() {
+        let dir = std::env::temp_dir().join(format!("repo2txt-git-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let repo = Repository::init(&dir).unwrap();
+
+        fs::write(dir.join("a.txt"), "1").unwrap();
+        fs::write(dir.join("b.txt"), "1").unwrap();
+        let init = commit(&repo, "init", &[]);
+        fs::write(dir.join("b.txt"), "2").unwrap();
+        let feature = commit(&repo, "feature b", &[init]);
+        fs::write(dir.join("b.txt"), "1").unwrap();
+        fs::write(dir.join("a.txt"), "2").unwrap();
+        let main = commit(&repo, "main a", &[init]);
+        fs::write(dir.join("b.txt"), "2").unwrap();
+        let merge = commit(&repo, "merge feature", &[main, feature]);
+        repo.set_head_detached(merge).unwrap();
+
+        let files: HashSet<String> = ["a.txt", "b.txt"].iter().map(|f| f.to_string()).collect();
+        let history = GitRepo::open(&dir).unwrap().file_history(&files).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(history["a.txt"].subject, "main a");
+        assert_eq!(history["a.txt"].commits, 2);
+        assert_eq!(history["b.txt"].subject, "feature b");
+        assert_eq!(history["b.txt"].commits, 2);
+    }
+}
@@ -0,0 +1,66 @@
+use std::path::Path;
+
+// Определение языка для подсветки по имени файла или расширению
+pub fn get_language_by_extension(file_path: &str) -> &str {
+    let file_name = Path::new(file_path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("");
+
+    // Special files
+    match file_name {
+        "Dockerfile" => return "dockerfile",
+        "Makefile" => return "makefile",
+        "LICENSE" => return "text",
+        "README" => return "markdown",
+        "CHANGELOG" => return "markdown",
+        ".gitignore" => return "gitignore",
+        ".gitattributes" => return "gitattributes",
+        ".env" => return "dotenv",
+        ".env.example" => return "dotenv",
+        _ => {}
+    }
+
+    if file_name == "docker-compose.yml" || file_name == "docker-compose.yaml" {
+        return "yaml";
+    }
+
+    // By extension
+    let ext = Path::new(file_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    match ext.as_str() {
+        "ts" => "typescript",
+        "js" => "javascript",
+        "tsx" => "tsx",
+        "jsx" => "jsx",
+        "json" => "json",
+        "md" => "markdown",
+        "yml" | "yaml" => "yaml",
+        "xml" => "xml",
+        "html" => "html",
+        "css" => "css",
+        "scss" => "scss",
+        "sass" => "sass",
+        "less" => "less",
+        "py" => "python",
+        "java" => "java",
+        "cpp" | "hpp" => "cpp",
+        "c" | "h" => "c",
+        "rs" => "rust",
+        "go" => "go",
+        "php" => "php",
+        "rb" => "ruby",
+        "sh" | "bash" | "zsh" => "bash",
+        "sql" => "sql",
+        "vue" => "vue",
+        "svelte" => "svelte",
+        "toml" => "toml",
+        "ini" => "ini",
+        "conf" | "config" => "conf",
+        _ => "text",
+    }
+}
@@ -0,0 +1,36 @@
+// Ядро repo2txt без зависимости от Tauri: сканирование, выбор файлов и генерация.
+// Команды Tauri и CLI - тонкие обертки над этими типами.
+pub mod analysis;
+pub mod autoselect;
+pub mod encoding;
+pub mod format;
+pub mod generated;
+pub mod generator;
+pub mod git;
+pub mod language;
+pub mod patterns;
+pub mod progress;
+pub mod scanner;
+pub mod search;
+pub mod secrets;
+pub mod selection;
+pub mod skeleton;
+pub mod split;
+pub mod strip;
+pub mod template;
+pub mod token_cache;
+pub mod tokens;
+pub mod transform;
+pub mod watcher;
+
+pub use format::OutputFormat;
+pub use generator::{GenerateOutput, Generator};
+pub use progress::{NoopProgress, ProgressReporter};
+pub use scanner::Scanner;
+pub use selection::Selection;
+pub use split::OutputPart;
+pub use template::Templates;
+pub use token_cache::TokenCache;
+pub use tokens::{Tokenizer, TokenizerKind};
+pub use transform::ContentTransform;
+pub use watcher::RepoWatcher;
@@ -0,0 +1,214 @@
+use crate::models::AppConfig;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use ignore::overrides::{Override, OverrideBuilder};
+use std::path::Path;
+
+// Символы glob: компонент пути с ними не является литеральным
+const GLOB_CHARS: &[char] = &['*', '?', '[', '{', '\\'];
+
+// Шаблоны путей в синтаксисе .gitignore из конфига: ignore_patterns исключают,
+// include_patterns (если заданы) оставляют только совпавшие файлы
+pub struct PathPatterns {
+    ignore: Gitignore,
+    include: Gitignore,
+    // Папки, через которые проходят include-шаблоны (`vendor/lib/*.rs` -> vendor, vendor/lib):
+    // их нельзя отбрасывать, даже если они игнорируются
+    include_dirs: Vec<String>,
+    overrides: Override,
+}
+
+impl PathPatterns {
+    pub fn compile(root: &Path, config: &AppConfig) -> Result<Self, String> {
+        let ignore = build_gitignore(root, &config.ignore_patterns, "ignore")?;
+        let include = build_gitignore(root, &config.include_patterns, "include")?;
+
+        let include_dirs: Vec<String> = config
+            .include_patterns
+            .iter()
+            .filter(|p| !p.starts_with('!'))
+            .flat_map(|p| literal_dirs(p))
+            .collect();
+
+        // Для обхода: include-шаблоны важнее .gitignore, остальные файлы не обходятся вовсе
+        let mut builder = OverrideBuilder::new(root);
+        for pattern in config.include_patterns.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            builder
+                .add(pattern)
+                .map_err(|e| format!("Invalid include pattern \"{}\": {}", pattern, e))?;
+        }
+        for dir in &include_dirs {
+            builder
+                .add(&format!("/{}", dir))
+                .map_err(|e| format!("Invalid include pattern \"{}\": {}", dir, e))?;
+        }
+        let overrides = builder.build().map_err(|e| format!("Invalid include patterns: {}", e))?;
+
+        Ok(Self {
+            ignore,
+            include,
+            include_dirs,
+            overrides,
+        })
+    }
+
+    pub fn empty() -> Self {
+        Self {
+            ignore: Gitignore::empty(),
+            include: Gitignore::empty(),
+            include_dirs: Vec::new(),
+            overrides: Override::empty(),
+        }
+    }
+
+    pub fn has_include(&self) -> bool {
+        !self.include.is_empty()
+    }
+
+    pub fn overrides(&self) -> &Override {
+        &self.overrides
+    }
+
+    pub fn is_ignored(&self, relative_path: &str, is_directory: bool) -> bool {
+        self.ignore.matched_path_or_any_parents(relative_path, is_directory).is_ignore()
+    }
+
+    // Файл совпал с include-шаблоном (сам или через папку-предка)
+    pub fn is_included(&self, relative_path: &str, is_directory: bool) -> bool {
+        self.include.matched_path_or_any_parents(relative_path, is_directory).is_ignore()
+    }
+
+    // Папка нужна include-шаблону: совпала с ним или лежит на его литеральном пути
+    pub fn is_include_dir(&self, relative_path: &str) -> bool {
+        self.is_included(relative_path, true) || self.include_dirs.iter().any(|dir| dir == relative_path)
+    }
+}
+
+// Правила приватных файлов (ключи, .env и т.п.): private_patterns скрывают файл,
+// private_allow возвращают его обратно. Регистр не учитывается.
+pub struct PrivateRules {
+    private: Gitignore,
+    allow: Gitignore,
+}
+
+impl PrivateRules {
+    pub fn compile(root: &Path, config: &AppConfig) -> Result<Self, String> {
+        Ok(Self {
+            private: build_gitignore_with_case(root, &config.private_patterns, "private file", true)?,
+            allow: build_gitignore_with_case(root, &config.private_allow, "allowed private file", true)?,
+        })
+    }
+
+    // Правила по умолчанию: ошибка в настройках не должна открывать приватные файлы
+    pub fn defaults(root: &Path) -> Self {
+        Self::compile(root, &AppConfig::default()).expect("default private patterns")
+    }
+
+    // Шаблон, по которому файл считается приватным (None - файл не приватный).
+    // Проверяется только сам файл: шаблон без `/` совпадает с именем на любой глубине.
+    pub fn matched_rule(&self, relative_path: &str) -> Option<String> {
+        let rule = match self.private.matched(relative_path, false) {
+            Match::Ignore(glob) => glob.original().to_string(),
+            _ => return None,
+        };
+        if self.allow.matched(relative_path, false).is_ignore() {
+            return None;
+        }
+        Some(rule)
+    }
+
+    pub fn is_private(&self, relative_path: &str) -> bool {
+        self.matched_rule(relative_path).is_some()
+    }
+}
+
+fn build_gitignore(root: &Path, patterns: &[String], kind: &str) -> Result<Gitignore, String> {
+    build_gitignore_with_case(root, patterns, kind, false)
+}
+
+fn build_gitignore_with_case(
+    root: &Path,
+    patterns: &[String],
+    kind: &str,
+    case_insensitive: bool,
+) -> Result<Gitignore, String> {
+    let mut builder = GitignoreBuilder::new(root);
+    builder
+        .case_insensitive(case_insensitive)
+        .map_err(|e| format!("Invalid {} patterns: {}", kind, e))?;
+    for pattern in patterns.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+        builder
+            .add_line(None, pattern)
+            .map_err(|e| format!("Invalid {} pattern \"{}\": {}", kind, pattern, e))?;
+    }
+    builder.build().map_err(|e| format!("Invalid {} patterns: {}", kind, e))
+}
+
+// Литеральные папки в начале шаблона с `/`: `docs/api/**/*.md` -> docs, docs/api.
+// Шаблон без `/` (кроме завершающего) совпадает на любой глубине - таких папок нет.
+fn literal_dirs(pattern: &str) -> Vec<String> {
+    let pattern = pattern.trim().trim_end_matches('/');
+    if !pattern.trim_start_matches('/').contains('/') {
+        return Vec::new();
+    }
+    let components: Vec<&str> = pattern.trim_start_matches('/').split('/').collect();
+    let mut dirs = Vec::new();
+    // Последний компонент - сам файл (или папка, совпадающая с шаблоном напрямую)
+    for i in 0..components.len() - 1 {
+        if components[i].contains(GLOB_CHARS) {
+            break;
+        }
+        dirs.push(components[..=i].join("/"));
+    }
+    dirs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literal_dirs_stop_at_first_glob() {
+        assert_eq!(literal_dirs("docs/api/**/*.md"), vec!["docs", "docs/api"]);
+        assert_eq!(literal_dirs("/src/engine/"), vec!["src"]);
+        assert_eq!(literal_dirs("src/*/mod.rs"), vec!["src"]);
+        assert_eq!(literal_dirs("**/tests/*.rs"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn default_private_rules_hide_keys_until_allowed() {
+        let rules = PrivateRules::defaults(Path::new("/repo"));
+        for path in [
+            ".env",
+            "config/.env.production",
+            "certs/server.pem",
+            "certs/Server.KEY",
+            "home/.ssh/id_rsa",
+            "home/.ssh/id_rsa.pub",
+            "deploy/secrets.yaml",
+            "api.secret",
+            "layouts/keyboard.key",
+            "src/secrets_test.rs",
+        ] {
+            assert!(rules.is_private(path), "{}", path);
+        }
+        assert!(!rules.is_private(".env.example"));
+        assert_eq!(rules.matched_rule("a/b/tls.key").as_deref(), Some("*.key"));
+
+        // Ложные срабатывания возвращаются через private_allow
+        let mut config = AppConfig::default();
+        config.private_allow.extend(["keyboard.key".to_string(), "**/secrets_test.rs".to_string()]);
+        let rules = PrivateRules::compile(Path::new("/repo"), &config).unwrap();
+        assert!(!rules.is_private("layouts/keyboard.key"));
+        assert!(!rules.is_private("src/secrets_test.rs"));
+        assert!(rules.is_private("certs/tls.key"));
+        assert!(rules.is_private("deploy/secrets.yaml"));
+    }
+
+    #[test]
+    fn patterns_without_slash_have_no_literal_dirs() {
+        assert!(literal_dirs("*.rs").is_empty());
+        assert!(literal_dirs("build/").is_empty());
+        assert!(literal_dirs("  target  ").is_empty());
+    }
+}
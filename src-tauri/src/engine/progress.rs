@@ -0,0 +1,18 @@
+use crate::models::{FileUpdate, ProgressEvent};
+
+// Получатель событий прогресса от ядра (в GUI - события Tauri, в CLI - ничего)
+pub trait ProgressReporter: Send + Sync {
+    // Прогресс генерации ("preparing", "processing", "writing", "completed")
+    fn on_progress(&self, _event: ProgressEvent) {}
+
+    // Батч результатов фонового анализа (размеры и токены)
+    fn on_files_updated(&self, _updates: &[FileUpdate]) {}
+
+    // Фоновый анализ завершен
+    fn on_analysis_completed(&self) {}
+}
+
+// Пустая реализация для встраивания без отслеживания прогресса
+pub struct NoopProgress;
+
+impl ProgressReporter for NoopProgress {}
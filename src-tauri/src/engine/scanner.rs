@@ -0,0 +1,432 @@
+use crate::engine::patterns::{PathPatterns, PrivateRules};
+use crate::engine::selection::Selection;
+use crate::models::{AppConfig, FileNode, PrivateFile};
+use ignore::WalkBuilder;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Вспомогательные структуры для парсинга .r2x (нужны только для чтения состояния)
+#[derive(Deserialize)]
+struct ConfigNodeSimple {
+    path: String, // relative path
+    selected: bool,
+    expanded: bool,
+    #[serde(default)]
+    skeleton: bool,
+    children: Option<Vec<ConfigNodeSimple>>,
+}
+
+#[derive(Deserialize)]
+struct R2XConfigSimple {
+    nodes: Vec<ConfigNodeSimple>,
+}
+
+// Сохраненное в .r2x состояние узла
+#[derive(Debug, Clone, Copy)]
+pub struct SavedState {
+    pub selected: bool,
+    pub expanded: bool,
+    pub skeleton: bool,
+}
+
+// Функция для превращения дерева конфига в плоскую карту состояний
+fn flatten_config_state(nodes: Vec<ConfigNodeSimple>, map: &mut HashMap<String, SavedState>) {
+    for node in nodes {
+        let state = SavedState {
+            selected: node.selected,
+            expanded: node.expanded,
+            skeleton: node.skeleton,
+        };
+        map.insert(node.path.clone(), state);
+        if let Some(children) = node.children {
+            flatten_config_state(children, map);
+        }
+    }
+}
+
+// Обход файловой системы с учетом .gitignore, .r2x_ignore и фильтров конфига
+pub struct Scanner {
+    root: PathBuf,
+    config: AppConfig,
+    patterns: PathPatterns,
+    private: PrivateRules,
+}
+
+impl Scanner {
+    pub fn new(root: impl Into<PathBuf>, config: AppConfig) -> Self {
+        let root = root.into();
+        // Шаблоны проверяются при сохранении настроек; здесь ошибка только отключает их
+        let patterns = match PathPatterns::compile(&root, &config) {
+            Ok(patterns) => patterns,
+            Err(e) => {
+                log::warn!("{}", e);
+                PathPatterns::empty()
+            }
+        };
+        let private = match PrivateRules::compile(&root, &config) {
+            Ok(rules) => rules,
+            Err(e) => {
+                log::warn!("{}; using default private file rules", e);
+                PrivateRules::defaults(&root)
+            }
+        };
+        Self {
+            root,
+            config,
+            patterns,
+            private,
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn config(&self) -> &AppConfig {
+        &self.config
+    }
+
+    // Загрузка сохраненного состояния выбора из .r2x (relative_path -> состояние)
+    pub fn load_saved_state(&self) -> Option<HashMap<String, SavedState>> {
+        let config_path = self.root.join(".r2x");
+        if !config_path.exists() {
+            log::debug!("No .r2x config found");
+            return None;
+        }
+
+        match fs::read_to_string(&config_path) {
+            Ok(content) => match serde_json::from_str::<R2XConfigSimple>(&content) {
+                Ok(conf) => {
+                    let mut map = HashMap::new();
+                    flatten_config_state(conf.nodes, &mut map);
+                    log::info!("Loaded .r2x config with {} entries", map.len());
+                    Some(map)
+                }
+                Err(e) => {
+                    log::warn!("Failed to parse .r2x: {}", e);
+                    None
+                }
+            },
+            Err(e) => {
+                log::warn!("Failed to read .r2x: {}", e);
+                None
+            }
+        }
+    }
+
+    fn walk_builder(&self, dir: &Path) -> WalkBuilder {
+        let mut builder = WalkBuilder::new(dir);
+        builder.hidden(false).git_ignore(true);
+        if self.patterns.has_include() {
+            builder.overrides(self.patterns.overrides().clone());
+        }
+
+        // Добавляем поддержку .r2x_ignore
+        let r2x_ignore_path = self.root.join(".r2x_ignore");
+        if r2x_ignore_path.exists() {
+            log::debug!("Found .r2x_ignore file, adding to ignore rules");
+            builder.add_ignore(&r2x_ignore_path);
+        }
+
+        builder
+    }
+
+    fn relative_path(&self, path: &Path) -> String {
+        path.strip_prefix(&self.root)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|_| path.to_string_lossy().to_string())
+    }
+
+    // Проверка по фильтрам конфига (игнорируемые папки/файлы и шаблоны, бинарные расширения,
+    // приватные файлы). Совпавшее с include_patterns не игнорируется, остальные файлы - да.
+    pub fn is_excluded(&self, relative_path: &str, is_directory: bool) -> bool {
+        self.is_filtered(relative_path, is_directory, true)
+    }
+
+    fn is_filtered(&self, relative_path: &str, is_directory: bool, check_private: bool) -> bool {
+        let path = Path::new(relative_path);
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+
+        if self.patterns.has_include() {
+            if is_directory {
+                if self.patterns.is_include_dir(relative_path) {
+                    return false;
+                }
+            } else if self.patterns.is_included(relative_path, false) {
+                // Файл в исключенной папке остался бы без родителя в дереве
+                let excluded_parent = path
+                    .ancestors()
+                    .skip(1)
+                    .filter_map(|p| p.to_str())
+                    .any(|p| !p.is_empty() && self.is_excluded(p, true));
+                return excluded_parent
+                    || self.is_binary(path, name)
+                    || (check_private && self.is_private(relative_path));
+            } else {
+                return true;
+            }
+        }
+
+        // Проверяем, содержит ли путь игнорируемые папки (например, .git в любом месте)
+        // В Rust пути всегда используют '/' в строковом представлении
+        if relative_path
+            .split('/')
+            .any(|component| self.config.ignored_folders.contains(component))
+        {
+            return true;
+        }
+
+        if self.patterns.is_ignored(relative_path, is_directory) {
+            return true;
+        }
+
+        // Для папок используем ignored_folders, для файлов - ignored_names
+        if is_directory {
+            return self.config.ignored_folders.contains(name);
+        }
+        if self.config.ignored_names.contains(name) {
+            return true;
+        }
+
+        self.is_binary(path, name) || (check_private && self.is_private(relative_path))
+    }
+
+    // Проверка расширения по конфигу
+    fn is_binary(&self, path: &Path, name: &str) -> bool {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if self.config.binary_extensions.contains(&ext.to_lowercase()) => {
+                log::debug!("Skipping binary file: {}", name);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn is_private(&self, relative_path: &str) -> bool {
+        if self.private.is_private(relative_path) {
+            log::debug!("Skipping private file: {}", relative_path);
+            return true;
+        }
+        false
+    }
+
+    // Файлы, скрытые только правилами приватности (остальные фильтры их бы пропустили),
+    // вместе с совпавшим шаблоном - чтобы было видно, что именно скрыто
+    pub fn private_files(&self) -> Vec<PrivateFile> {
+        let mut files: Vec<PrivateFile> = self
+            .walk_builder(&self.root)
+            .build()
+            .flatten()
+            .filter(|entry| entry.file_type().is_some_and(|ft| ft.is_file()))
+            .filter_map(|entry| {
+                let relative_path = self.relative_path(entry.path());
+                let rule = self.private.matched_rule(&relative_path)?;
+                if self.is_filtered(&relative_path, false, false) {
+                    return None;
+                }
+                Some(PrivateFile {
+                    path: relative_path,
+                    rule,
+                })
+            })
+            .collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        files
+    }
+
+    // Создание узла для пути внутри проекта; состояние выбора берется из сохраненной карты
+    fn make_node(
+        &self,
+        entry_path: &Path,
+        is_directory: bool,
+        saved_state: Option<&HashMap<String, SavedState>>,
+    ) -> FileNode {
+        let relative_path = self.relative_path(entry_path);
+        let name = entry_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("");
+
+        // Parent ID: относительный путь родителя (None для элементов в корне)
+        let parent_id = Path::new(&relative_path)
+            .parent()
+            .map(|p| p.to_string_lossy().to_string())
+            .filter(|p| !p.is_empty());
+
+        // --- ЛОГИКА СЛИЯНИЯ (MERGE) ---
+        // По умолчанию selected = true (если файла нет в конфиге, считаем новым и выбираем)
+        let saved = saved_state.and_then(|map| map.get(&relative_path).copied());
+        let (selected, expanded) = saved.map(|s| (s.selected, s.expanded)).unwrap_or((true, false));
+
+        FileNode {
+            id: relative_path.clone(),
+            parent_id,
+            name: name.to_string(),
+            path: entry_path.to_string_lossy().to_string(),
+            relative_path,
+            is_directory,
+            size: None,        // <--- ВАЖНО: Пока не знаем размер
+            token_count: None, // <--- ВАЖНО: Пока не знаем токены
+            selected,          // <-- Применили из конфига
+            expanded,          // <-- Применили из конфига
+            skeleton: saved.map(|s| s.skeleton).unwrap_or(false),
+            class: None,
+            encoding: None,
+        }
+    }
+
+    // Обход директории со всеми вложенными элементами (сама директория не включается)
+    fn walk_into(
+        &self,
+        dir: &Path,
+        saved_state: Option<&HashMap<String, SavedState>>,
+        nodes: &mut Vec<FileNode>,
+    ) {
+        for entry in self.walk_builder(dir).build().flatten() {
+            if entry.depth() == 0 {
+                continue;
+            }
+
+            // Получаем только тип файла, без размера (file_type быстрее metadata)
+            let is_directory = entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false);
+
+            let relative_path = self.relative_path(entry.path());
+            if self.is_excluded(&relative_path, is_directory) {
+                continue;
+            }
+
+            nodes.push(self.make_node(entry.path(), is_directory, saved_state));
+        }
+    }
+
+    // Полное сканирование проекта (Этап 1) со слиянием состояния из .r2x.
+    // Размеры и токены не вычисляются - это задача фонового анализа.
+    pub fn scan(&self) -> Selection {
+        self.scan_with_state(self.load_saved_state().as_ref())
+    }
+
+    // То же, но с уже прочитанным состоянием .r2x
+    pub fn scan_with_state(&self, saved_state: Option<&HashMap<String, SavedState>>) -> Selection {
+        let mut nodes = Vec::new();
+        self.walk_into(&self.root, saved_state, &mut nodes);
+
+        // С include-шаблонами папки без подходящих файлов не нужны
+        if self.patterns.has_include() {
+            let mut used_dirs = std::collections::HashSet::new();
+            for node in nodes.iter().filter(|n| !n.is_directory) {
+                let mut parent = node.parent_id.as_deref();
+                while let Some(dir) = parent {
+                    if !used_dirs.insert(dir.to_string()) {
+                        break;
+                    }
+                    parent = Path::new(dir).parent().and_then(|p| p.to_str()).filter(|p| !p.is_empty());
+                }
+            }
+            nodes.retain(|n| !n.is_directory || used_dirs.contains(&n.id));
+        }
+
+        let mut selection = Selection::new();
+        for node in nodes {
+            selection.insert(node);
+        }
+        selection
+    }
+
+    // Сканирование одного пути (файла или папки со всем содержимым).
+    // Пустой результат, если путь не существует или исключен фильтрами/.gitignore.
+    pub fn scan_subtree(&self, relative_path: &str) -> Vec<FileNode> {
+        let target = self.root.join(relative_path);
+        let parent = match target.parent() {
+            Some(p) if target != self.root => p,
+            _ => return Vec::new(),
+        };
+
+        // Ищем путь среди элементов родителя, чтобы применились правила .gitignore
+        let entry = self
+            .walk_builder(parent)
+            .max_depth(Some(1))
+            .build()
+            .flatten()
+            .find(|e| e.depth() == 1 && e.path() == target);
+        let entry = match entry {
+            Some(e) => e,
+            None => return Vec::new(),
+        };
+
+        let is_directory = entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false);
+        if self.is_excluded(relative_path, is_directory) {
+            return Vec::new();
+        }
+
+        let mut nodes = vec![self.make_node(&target, is_directory, None)];
+        if is_directory {
+            self.walk_into(&target, None, &mut nodes);
+        }
+        nodes
+    }
+
+    // Сканирование одного уровня директории parent_id; возвращает только новые узлы
+    pub fn scan_directory(&self, parent_id: &str, existing: &Selection) -> Vec<FileNode> {
+        let dir_path = self.root.join(parent_id);
+        let walker = self
+            .walk_builder(&dir_path)
+            .max_depth(Some(1)) // Только первый уровень
+            .build();
+
+        let mut new_nodes = Vec::new();
+
+        for result in walker {
+            let entry = match result {
+                Ok(entry) => entry,
+                Err(err) => {
+                    log::warn!("Error scanning directory entry: {}", err);
+                    continue;
+                }
+            };
+            if entry.depth() == 0 {
+                continue; // Пропускаем саму директорию
+            }
+
+            let entry_path = entry.path();
+            let metadata = match entry.metadata() {
+                Ok(m) => m,
+                Err(_) => continue,
+            };
+            let is_directory = metadata.is_dir();
+
+            let relative_path = self.relative_path(entry_path);
+            if self.is_excluded(&relative_path, is_directory) {
+                continue;
+            }
+
+            // Проверяем, существует ли уже узел с таким ID
+            if existing.contains(&relative_path) {
+                continue;
+            }
+
+            new_nodes.push(FileNode {
+                id: relative_path.clone(),
+                parent_id: Some(parent_id.to_string()),
+                name: entry_path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or("")
+                    .to_string(),
+                path: entry_path.to_string_lossy().to_string(),
+                relative_path,
+                is_directory,
+                size: Some(if is_directory { 0 } else { metadata.len() }),
+                token_count: None,
+                selected: true,
+                expanded: false,
+                skeleton: false,
+                class: None,
+                encoding: None,
+            });
+        }
+
+        new_nodes
+    }
+}
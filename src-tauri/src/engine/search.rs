@@ -0,0 +1,129 @@
+use crate::engine::encoding::decode;
+use crate::models::FileNode;
+use futures::{stream, StreamExt};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+// Больше совпадений не возвращаем (список файлов при этом полный)
+const MAX_MATCHES: usize = 1000;
+// Длина фрагмента строки вокруг совпадения (в символах)
+const SNIPPET_CHARS: usize = 200;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ContentQuery {
+    pub pattern: String,
+    #[serde(default)]
+    pub regex: bool, // false - поиск подстроки
+    #[serde(default)]
+    pub case_sensitive: bool,
+}
+
+impl ContentQuery {
+    pub fn compile(&self) -> Result<Regex, String> {
+        if self.pattern.is_empty() {
+            return Err("Search query is empty".to_string());
+        }
+        let pattern = match self.regex {
+            true => self.pattern.clone(),
+            false => regex::escape(&self.pattern),
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+            .map_err(|e| format!("Invalid search pattern: {}", e))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ContentMatch {
+    pub id: String,
+    pub path: String,
+    pub line: usize, // С 1
+    pub snippet: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ContentSearch {
+    pub files: Vec<String>, // id всех файлов с совпадениями
+    pub matches: Vec<ContentMatch>,
+    pub truncated: bool, // matches обрезаны до MAX_MATCHES
+}
+
+// Поиск по содержимому файлов; пропускает то же, что и генерация (больше max_file_size, бинарные)
+pub async fn search_files(files: Vec<FileNode>, regex: &Regex, max_file_size: u64) -> ContentSearch {
+    let mut found: Vec<(FileNode, Vec<ContentMatch>)> = stream::iter(files)
+        .map(|node| async move {
+            let matches = search_file(&node, regex, max_file_size).await;
+            (node, matches)
+        })
+        .buffer_unordered(50) // 50 файлов параллельно
+        .filter(|(_, matches)| std::future::ready(!matches.is_empty()))
+        .collect()
+        .await;
+    found.sort_by(|a, b| a.0.relative_path.cmp(&b.0.relative_path));
+
+    let mut result = ContentSearch::default();
+    for (node, matches) in found {
+        result.files.push(node.id);
+        let room = MAX_MATCHES - result.matches.len();
+        if matches.len() > room {
+            result.truncated = true;
+        }
+        result.matches.extend(matches.into_iter().take(room));
+    }
+    result
+}
+
+async fn search_file(node: &FileNode, regex: &Regex, max_file_size: u64) -> Vec<ContentMatch> {
+    match fs::metadata(&node.path).await {
+        Ok(meta) if meta.len() <= max_file_size => {}
+        _ => return Vec::new(),
+    }
+    let bytes = match fs::read(&node.path).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            log::warn!("Failed to read {} for search: {}", node.path, e);
+            return Vec::new();
+        }
+    };
+    let content = match decode(&bytes) {
+        Some((content, _)) => content,
+        None => return Vec::new(), // Бинарный файл
+    };
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let found = regex.find(line)?;
+            Some(ContentMatch {
+                id: node.id.clone(),
+                path: node.relative_path.clone(),
+                line: i + 1,
+                snippet: snippet(line, found.start()),
+            })
+        })
+        .collect()
+}
+
+// Строка без отступа; длинная - обрезается вокруг совпадения
+fn snippet(line: &str, match_start: usize) -> String {
+    let line = line.trim_end();
+    let indent = line.len() - line.trim_start().len();
+    let text = &line[indent..];
+    if text.chars().count() <= SNIPPET_CHARS {
+        return text.to_string();
+    }
+
+    // Начинаем немного раньше совпадения, чтобы был виден контекст
+    let before = text[..match_start.saturating_sub(indent).min(text.len())].chars().count();
+    let skip = before.saturating_sub(SNIPPET_CHARS / 4);
+    let mut result: String = text.chars().skip(skip).take(SNIPPET_CHARS).collect();
+    if skip > 0 {
+        result.insert(0, '…');
+    }
+    if skip + SNIPPET_CHARS < text.chars().count() {
+        result.push('…');
+    }
+    result
+}
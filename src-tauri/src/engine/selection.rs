@@ -0,0 +1,217 @@
+use crate::models::FileNode;
+use std::collections::HashMap;
+
+// Плоский список узлов (id == relative_path) с состоянием выбора.
+// Храним HashMap для быстрого доступа O(1).
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    nodes: HashMap<String, FileNode>,
+}
+
+impl Selection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_nodes(nodes: HashMap<String, FileNode>) -> Self {
+        Self { nodes }
+    }
+
+    pub fn nodes(&self) -> &HashMap<String, FileNode> {
+        &self.nodes
+    }
+
+    pub fn nodes_mut(&mut self) -> &mut HashMap<String, FileNode> {
+        &mut self.nodes
+    }
+
+    pub fn into_nodes(self) -> HashMap<String, FileNode> {
+        self.nodes
+    }
+
+    pub fn get(&self, id: &str) -> Option<&FileNode> {
+        self.nodes.get(id)
+    }
+
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut FileNode> {
+        self.nodes.get_mut(id)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.nodes.contains_key(id)
+    }
+
+    pub fn insert(&mut self, node: FileNode) {
+        self.nodes.insert(node.id.clone(), node);
+    }
+
+    pub fn remove(&mut self, id: &str) -> Option<FileNode> {
+        self.nodes.remove(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    // Все узлы списком: папки сверху, затем по имени
+    pub fn sorted_nodes(&self) -> Vec<FileNode> {
+        let mut result: Vec<FileNode> = self.nodes.values().cloned().collect();
+        result.sort_by(|a, b| {
+            if a.is_directory != b.is_directory {
+                return b.is_directory.cmp(&a.is_directory);
+            }
+            a.name.cmp(&b.name)
+        });
+        result
+    }
+
+    // Выбор узла; для директорий - рекурсивно для всех вложенных элементов
+    pub fn set_selected(&mut self, id: &str, selected: bool) {
+        self.update_subtree(id, |node| node.selected = selected);
+    }
+
+    // Режим скелета (только сигнатуры); для директорий - рекурсивно
+    pub fn set_skeleton(&mut self, id: &str, skeleton: bool) {
+        self.update_subtree(id, |node| node.skeleton = skeleton);
+    }
+
+    // Изменение узла и, для директорий, всех вложенных элементов
+    fn update_subtree(&mut self, id: &str, update: impl Fn(&mut FileNode)) {
+        let is_directory = match self.nodes.get_mut(id) {
+            Some(node) => {
+                update(node);
+                node.is_directory
+            }
+            None => return,
+        };
+
+        // Используем итеративный подход с префиксом пути для эффективности
+        if is_directory {
+            let target_prefix = format!("{}/", id);
+
+            // Обновляем все дочерние элементы (прямые и вложенные)
+            // Внимание: это работает только если id == relative_path
+            for (key, child) in self.nodes.iter_mut() {
+                if key.starts_with(&target_prefix) {
+                    update(child);
+                }
+            }
+        }
+    }
+
+    pub fn set_expanded(&mut self, id: &str, expanded: bool) {
+        if let Some(node) = self.nodes.get_mut(id) {
+            node.expanded = expanded;
+        }
+    }
+
+    pub fn select_all(&mut self) {
+        for node in self.nodes.values_mut() {
+            if !node.is_directory {
+                node.selected = true;
+            }
+        }
+    }
+
+    pub fn deselect_all(&mut self) {
+        for node in self.nodes.values_mut() {
+            node.selected = false;
+        }
+    }
+
+    // Выбрать ровно указанные файлы (по id); папки выбираются, только если внутри есть выбранный файл.
+    // Возвращает id выбранных файлов, найденных в дереве.
+    pub fn select_only(&mut self, ids: &[String]) -> Vec<String> {
+        let mut selected_files = Vec::new();
+        let mut selected_dirs = std::collections::HashSet::new();
+
+        for id in ids {
+            let is_file = matches!(self.nodes.get(id), Some(node) if !node.is_directory);
+            if !is_file {
+                continue;
+            }
+            selected_files.push(id.clone());
+
+            // Все предки должны быть выбраны, иначе файл не попадет в генерацию
+            let mut parent = self.nodes.get(id).and_then(|n| n.parent_id.clone());
+            while let Some(parent_id) = parent {
+                if !selected_dirs.insert(parent_id.clone()) {
+                    break;
+                }
+                parent = self.nodes.get(&parent_id).and_then(|n| n.parent_id.clone());
+            }
+        }
+
+        let selected_set: std::collections::HashSet<&String> = selected_files.iter().collect();
+        for (id, node) in self.nodes.iter_mut() {
+            node.selected = if node.is_directory {
+                selected_dirs.contains(id)
+            } else {
+                selected_set.contains(id)
+            };
+        }
+
+        selected_files
+    }
+
+    // Выбор или снятие выбора с файлов (по id); при выборе выбираются и их папки.
+    // Возвращает id изменившихся узлов.
+    pub fn set_files_selected(&mut self, ids: &[String], selected: bool) -> Vec<String> {
+        let mut changed = Vec::new();
+        for id in ids {
+            let mut current = match self.nodes.get(id) {
+                Some(node) if !node.is_directory => Some(id.clone()),
+                _ => continue,
+            };
+            while let Some(node_id) = current {
+                let node = match self.nodes.get_mut(&node_id) {
+                    Some(node) => node,
+                    None => break,
+                };
+                if node.selected == selected {
+                    // Предки уже выбраны (или это снятие выбора - папки не трогаем)
+                    break;
+                }
+                node.selected = selected;
+                changed.push(node_id);
+                current = if selected { node.parent_id.clone() } else { None };
+            }
+        }
+        changed
+    }
+
+    // Поиск по имени (без учета регистра), возвращает id найденных узлов
+    pub fn search(&self, query: &str) -> Vec<String> {
+        let query_lower = query.to_lowercase();
+        self.nodes
+            .values()
+            .filter(|n| n.name.to_lowercase().contains(&query_lower))
+            .map(|n| n.id.clone())
+            .collect()
+    }
+
+    // Выбранные файлы для генерации: только файлы, только выбранные, и родители тоже должны быть выбраны
+    pub fn selected_files(&self) -> Vec<FileNode> {
+        self.nodes
+            .values()
+            .filter(|n| {
+                if n.is_directory || !n.selected {
+                    return false;
+                }
+                if let Some(ref parent_id) = n.parent_id {
+                    if let Some(parent) = self.nodes.get(parent_id) {
+                        if !parent.selected {
+                            return false;
+                        }
+                    }
+                }
+                true
+            })
+            .cloned()
+            .collect()
+    }
+}
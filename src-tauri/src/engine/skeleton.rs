@@ -0,0 +1,388 @@
+use crate::engine::strip::{code_mask, Syntax};
+
+// Маркер вместо тела функции
+const BRACE_BODY: &str = "{ ... }";
+const PYTHON_BODY: &str = "...";
+
+// Управляющие конструкции JS/TS: их блоки - не тела функций
+const JS_CONTROL: &[&str] = &["if", "else", "for", "while", "do", "switch", "try", "catch", "finally", "with"];
+// Объявления, чьи блоки сохраняются целиком (методы внутри обрабатываются отдельно)
+const JS_CONTAINERS: &[&str] = &["class", "interface", "enum", "namespace", "module", "type"];
+
+// Скелет файла: текст и исходный номер (с 1) каждой его строки
+pub struct Skeleton {
+    pub text: String,
+    pub line_numbers: Vec<usize>,
+}
+
+// Сигнатуры типов, трейтов, классов и функций вместе с doc-комментариями; тела функций
+// заменяются маркером. None - язык не поддерживается или файл не удалось разобрать.
+pub fn skeleton(content: &str, language: &str) -> Option<Skeleton> {
+    let syntax = match Syntax::for_language(language)? {
+        syntax @ (Syntax::Rust | Syntax::JavaScript | Syntax::Go | Syntax::Python) => syntax,
+        _ => return None,
+    };
+    let mask = code_mask(content, syntax)?;
+    match syntax {
+        Syntax::Python => python(content, &mask),
+        _ => braces(content, &mask, syntax),
+    }
+}
+
+// Сборка результата из кусков исходного текста с учетом номеров строк
+struct Emitter<'a> {
+    src: &'a str,
+    line_starts: Vec<usize>,
+    text: String,
+    line_numbers: Vec<usize>,
+}
+
+impl<'a> Emitter<'a> {
+    fn new(src: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            src,
+            line_starts,
+            text: String::with_capacity(src.len() / 2),
+            line_numbers: Vec::new(),
+        }
+    }
+
+    fn line_of(&self, offset: usize) -> usize {
+        self.line_starts.partition_point(|&start| start <= offset)
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.text.is_empty() || self.text.ends_with('\n')
+    }
+
+    fn copy(&mut self, start: usize, end: usize) {
+        let mut offset = start;
+        for piece in self.src[start..end].split_inclusive('\n') {
+            if self.at_line_start() {
+                self.line_numbers.push(self.line_of(offset));
+            }
+            self.text.push_str(piece);
+            offset += piece.len();
+        }
+    }
+
+    // Вставка маркера; `line` - номер строки, если маркер начинает новую строку
+    fn insert(&mut self, marker: &str, line: usize) {
+        if self.at_line_start() {
+            self.line_numbers.push(line);
+        }
+        self.text.push_str(marker);
+    }
+
+    fn finish(self) -> Skeleton {
+        Skeleton {
+            text: self.text,
+            line_numbers: self.line_numbers,
+        }
+    }
+}
+
+// Только код (комментарии и литералы заменены пробелами) - для разбора заголовков
+fn code_text(content: &str, mask: &[bool], start: usize, end: usize) -> String {
+    let bytes: Vec<u8> = content.as_bytes()[start..end]
+        .iter()
+        .zip(&mask[start..end])
+        .map(|(&b, &code)| if code { b } else { b' ' })
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .filter(|w| !w.is_empty())
+}
+
+// Rust, JS/TS, Go: тело функции - блок `{}`, перед которым стоит сигнатура функции
+fn braces(content: &str, mask: &[bool], syntax: Syntax) -> Option<Skeleton> {
+    let bytes = content.as_bytes();
+    let mut emitter = Emitter::new(content);
+    let mut header_start = 0; // Начало текста перед очередным `{`
+    let mut copied = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if !mask[i] {
+            i += 1;
+            continue;
+        }
+        match bytes[i] {
+            b';' | b'}' => header_start = i + 1,
+            // В Go сигнатура функции всегда на одной строке с `{`
+            b'\n' if syntax == Syntax::Go => header_start = i + 1,
+            b'{' => {
+                let header = code_text(content, mask, header_start, i);
+                if is_function_header(&header, syntax) {
+                    let close = matching_brace(bytes, mask, i)?;
+                    // Пустое тело `{}` остается; тело из одного литерала (`{ "name" }`) - тоже тело
+                    if !content[i + 1..close].trim().is_empty() {
+                        emitter.copy(copied, i);
+                        emitter.insert(BRACE_BODY, emitter.line_of(i));
+                        copied = close + 1;
+                    }
+                    i = close + 1;
+                    header_start = i;
+                    continue;
+                }
+                header_start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    emitter.copy(copied, bytes.len());
+    Some(emitter.finish())
+}
+
+fn matching_brace(bytes: &[u8], mask: &[bool], open: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for i in open..bytes.len() {
+        if !mask[i] {
+            continue;
+        }
+        match bytes[i] {
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn is_function_header(header: &str, syntax: Syntax) -> bool {
+    let mut header_words = words(header);
+    match syntax {
+        Syntax::Rust => header_words.any(|w| w == "fn"),
+        Syntax::Go => header_words.any(|w| w == "func"),
+        _ => {
+            let trimmed = header.trim();
+            let first = header_words.next().unwrap_or("");
+            if JS_CONTROL.contains(&first) || words(header).any(|w| JS_CONTAINERS.contains(&w)) {
+                return false;
+            }
+            if words(header).any(|w| w == "function") || trimmed.ends_with("=>") {
+                return true;
+            }
+            is_method_signature(trimmed)
+        }
+    }
+}
+
+// `name(args)` или `name(args): ReturnType` со сбалансированными скобками
+fn is_method_signature(header: &str) -> bool {
+    let close = match header.rfind(')') {
+        Some(close) => close,
+        None => return false,
+    };
+    let mut depth = 0i32;
+    for c in header.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        if depth < 0 {
+            return false;
+        }
+    }
+    let rest = header[close + 1..].trim_start();
+    depth == 0 && (rest.is_empty() || rest.starts_with(':'))
+}
+
+// Python: тело `def` - строки с большим отступом; docstring сохраняется
+fn python(content: &str, mask: &[bool]) -> Option<Skeleton> {
+    let lines: Vec<(usize, &str)> = content
+        .split_inclusive('\n')
+        .scan(0, |offset, line| {
+            let start = *offset;
+            *offset += line.len();
+            Some((start, line))
+        })
+        .collect();
+    // Строка начинается внутри многострочного литерала, если перевод строки перед ней - не код
+    let in_literal = |k: usize| lines[k].0 > 0 && !mask[lines[k].0 - 1];
+    let indent = |k: usize| lines[k].1.len() - lines[k].1.trim_start_matches([' ', '\t']).len();
+    // Пустые строки, комментарии и продолжения литералов не определяют границы тела
+    let neutral = |k: usize| {
+        let text = lines[k].1.trim();
+        in_literal(k) || text.is_empty() || (text.starts_with('#') && !mask[lines[k].0 + indent(k)])
+    };
+
+    let mut emitter = Emitter::new(content);
+    let mut k = 0;
+    while k < lines.len() {
+        let (start, line) = lines[k];
+        let def_indent = indent(k);
+        let trimmed = &line[def_indent..];
+        let is_def = (trimmed.starts_with("def ") || trimmed.starts_with("async def "))
+            && !in_literal(k)
+            && mask[start + def_indent];
+        if !is_def {
+            emitter.copy(start, start + line.len());
+            k += 1;
+            continue;
+        }
+
+        // Конец сигнатуры - `:` вне скобок (аннотации аргументов внутри скобок)
+        let colon = signature_colon(content, mask, start + def_indent)?;
+        let colon_line = k + lines[k..].iter().take_while(|(s, l)| s + l.len() <= colon).count();
+        let colon_line_end = lines[colon_line].0 + lines[colon_line].1.len();
+        let one_liner = !code_text(content, mask, colon + 1, colon_line_end).trim().is_empty();
+
+        let mut last_body = None;
+        if !one_liner {
+            for j in colon_line + 1..lines.len() {
+                if neutral(j) {
+                    continue;
+                }
+                if indent(j) <= def_indent {
+                    break;
+                }
+                last_body = Some(j);
+            }
+        }
+        let last_body = match last_body {
+            Some(last) => last,
+            None => {
+                emitter.copy(start, colon_line_end);
+                k = colon_line + 1;
+                continue;
+            }
+        };
+
+        // Первая инструкция тела - строка? Тогда это docstring, сохраняем его целиком
+        let first = (colon_line + 1..=last_body).find(|&j| !neutral(j))?;
+        let mut kept = colon_line;
+        if starts_with_string(&lines[first].1[indent(first)..]) {
+            kept = first;
+            while kept < last_body && in_literal(kept + 1) {
+                kept += 1;
+            }
+        }
+
+        emitter.copy(start, lines[kept].0 + lines[kept].1.len());
+        if kept < last_body {
+            let body_indent = &lines[first].1[..indent(first)];
+            let ending = &lines[first].1[lines[first].1.trim_end_matches(['\n', '\r']).len()..];
+            let ending = if ending.is_empty() { "\n" } else { ending };
+            emitter.insert(&format!("{}{}{}", body_indent, PYTHON_BODY, ending), emitter.line_of(lines[kept + 1].0));
+        }
+        k = last_body + 1;
+    }
+    Some(emitter.finish())
+}
+
+fn signature_colon(content: &str, mask: &[bool], start: usize) -> Option<usize> {
+    let mut depth = 0i32;
+    for (i, b) in content.bytes().enumerate().skip(start) {
+        if !mask[i] {
+            continue;
+        }
+        match b {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            b':' if depth == 0 => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+// Строковый литерал с необязательным префиксом (r, b, u, f и их сочетания)
+fn starts_with_string(text: &str) -> bool {
+    let rest = text.trim_start_matches(|c: char| matches!(c.to_ascii_lowercase(), 'r' | 'b' | 'u' | 'f'));
+    text.len() - rest.len() <= 2 && (rest.starts_with('"') || rest.starts_with('\''))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(skeleton: &Skeleton) -> Vec<(usize, &str)> {
+        skeleton.line_numbers.iter().copied().zip(skeleton.text.lines()).collect()
+    }
+
+    #[test]
+    fn elides_rust_function_bodies() {
+        let content = "/// Adds one\npub fn inc(x: u32) -> u32 {\n    // comment { brace\n    x + 1\n}\n\nimpl Foo {\n    fn empty() {}\n    fn get(&self) -> &str {\n        \"}\"\n    }\n}\n";
+        let skeleton = skeleton(content, "rust").unwrap();
+        assert_eq!(
+            lines(&skeleton),
+            vec![
+                (1, "/// Adds one"),
+                (2, "pub fn inc(x: u32) -> u32 { ... }"),
+                (6, ""),
+                (7, "impl Foo {"),
+                (8, "    fn empty() {}"),
+                (9, "    fn get(&self) -> &str { ... }"),
+                (12, "}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn elides_js_functions_but_keeps_classes_and_control_flow() {
+        let content = "class A {\n  run(x: number): void {\n    if (x) {\n      go();\n    }\n  }\n}\nconst f = (a) => {\n  return a;\n};\nif (ready) {\n  start();\n}\n";
+        let skeleton = skeleton(content, "typescript").unwrap();
+        assert_eq!(
+            lines(&skeleton),
+            vec![
+                (1, "class A {"),
+                (2, "  run(x: number): void { ... }"),
+                (7, "}"),
+                (8, "const f = (a) => { ... };"),
+                (11, "if (ready) {"),
+                (12, "  start();"),
+                (13, "}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn elides_go_function_bodies() {
+        let content = "type T struct {\n\tA int\n}\n\nfunc (t T) Get() int {\n\treturn t.A\n}\n";
+        let skeleton = skeleton(content, "go").unwrap();
+        assert_eq!(
+            lines(&skeleton),
+            vec![(1, "type T struct {"), (2, "\tA int"), (3, "}"), (4, ""), (5, "func (t T) Get() int { ... }")]
+        );
+    }
+
+    #[test]
+    fn keeps_python_docstrings() {
+        let content = "class A:\n    def f(self, x: int) -> int:\n        \"\"\"Doc\n        more.\"\"\"\n        y = x\n\n        return y\n\n    def g(self): return 1\n\nz = 1\n";
+        let skeleton = skeleton(content, "python").unwrap();
+        assert_eq!(
+            lines(&skeleton),
+            vec![
+                (1, "class A:"),
+                (2, "    def f(self, x: int) -> int:"),
+                (3, "        \"\"\"Doc"),
+                (4, "        more.\"\"\""),
+                (5, "        ..."),
+                (8, ""),
+                (9, "    def g(self): return 1"),
+                (10, ""),
+                (11, "z = 1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_unsupported_or_unparsable_files() {
+        assert!(skeleton("int main() { return 0; }", "c").is_none());
+        assert!(skeleton("fn f() { let s = \"open; }\n", "rust").is_none());
+    }
+}
@@ -0,0 +1,154 @@
+use crate::engine::tokens::Tokenizer;
+use crate::models::AppStats;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Готовый к выводу элемент части: файл (кусок большого файла) или служебный раздел
+pub struct PartItem {
+    pub label: Option<String>, // Путь для оглавления части; None для служебных разделов (Changes)
+    pub content: String,
+    pub size: u64,
+    pub tokens: usize,
+}
+
+// Одна часть разбитого документа. stats.tokens - фактический размер части вместе с заголовком.
+pub struct OutputPart {
+    pub content: String,
+    pub stats: AppStats,
+}
+
+// Раскладка элементов по частям с сохранением порядка. `base_tokens` - заголовок и завершение
+// каждой части. Элемент не делится: если он один больше бюджета, он занимает отдельную часть
+// (большие файлы заранее режутся split_lines).
+pub fn pack(items: Vec<PartItem>, budget: usize, base_tokens: usize) -> Vec<Vec<PartItem>> {
+    let mut groups: Vec<Vec<PartItem>> = vec![Vec::new()];
+    let mut current_tokens = base_tokens;
+
+    for item in items {
+        let current = groups.last_mut().expect("groups is never empty");
+        if !current.is_empty() && current_tokens + item.tokens > budget {
+            groups.push(Vec::new());
+            current_tokens = base_tokens;
+        }
+        current_tokens += item.tokens;
+        groups.last_mut().expect("groups is never empty").push(item);
+    }
+    groups
+}
+
+// Нарезка содержимого файла по строкам на куски не больше `max_tokens`.
+// Строка длиннее лимита остается целой. Возвращает (первая строка, последняя строка, текст).
+pub fn split_lines(content: &str, max_tokens: usize, tokenizer: &Tokenizer) -> Vec<(usize, usize, String)> {
+    let mut pieces = Vec::new();
+    let mut piece = String::new();
+    let mut piece_tokens = 0;
+    let mut first_line = 1;
+    let mut line_count = 0;
+
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        line_count = i + 1;
+        let line_tokens = tokenizer.count(line);
+        if !piece.is_empty() && piece_tokens + line_tokens > max_tokens {
+            pieces.push((first_line, i, std::mem::take(&mut piece)));
+            piece_tokens = 0;
+            first_line = i + 1;
+        }
+        piece.push_str(line);
+        piece_tokens += line_tokens;
+    }
+    if !piece.is_empty() {
+        pieces.push((first_line, line_count, piece));
+    }
+    pieces
+}
+
+// output.md -> output-part-2.md
+pub fn part_path(path: &Path, index: usize) -> PathBuf {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let file_name = match path.extension() {
+        Some(ext) => format!("{}-part-{}.{}", stem, index, ext.to_string_lossy()),
+        None => format!("{}-part-{}", stem, index),
+    };
+    path.with_file_name(file_name)
+}
+
+// Запись частей рядом с `path`; части от прошлой генерации с большими номерами удаляются
+pub fn write_parts(path: &Path, parts: &[OutputPart]) -> Result<Vec<PathBuf>, String> {
+    let mut written = Vec::with_capacity(parts.len());
+    for (i, part) in parts.iter().enumerate() {
+        let part_file = part_path(path, i + 1);
+        fs::write(&part_file, &part.content)
+            .map_err(|e| format!("Failed to write file at {}: {}", part_file.display(), e))?;
+        written.push(part_file);
+    }
+
+    let mut index = parts.len() + 1;
+    loop {
+        let stale = part_path(path, index);
+        if fs::remove_file(&stale).is_err() {
+            break;
+        }
+        log::debug!("Removed stale part {}", stale.display());
+        index += 1;
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(name: &str, tokens: usize) -> PartItem {
+        PartItem {
+            label: Some(name.to_string()),
+            content: String::new(),
+            size: 0,
+            tokens,
+        }
+    }
+
+    fn labels(groups: &[Vec<PartItem>]) -> Vec<Vec<&str>> {
+        groups
+            .iter()
+            .map(|group| group.iter().map(|item| item.label.as_deref().unwrap_or("")).collect())
+            .collect()
+    }
+
+    #[test]
+    fn packs_items_in_order_within_budget() {
+        let items = vec![item("a", 40), item("b", 40), item("c", 30), item("d", 10)];
+        // Бюджет 100, заголовок каждой части - 20 токенов
+        let groups = pack(items, 100, 20);
+        assert_eq!(labels(&groups), vec![vec!["a", "b"], vec!["c", "d"]]);
+    }
+
+    #[test]
+    fn oversized_item_gets_its_own_part() {
+        let items = vec![item("a", 10), item("big", 500), item("b", 10)];
+        let groups = pack(items, 100, 0);
+        assert_eq!(labels(&groups), vec![vec!["a"], vec!["big"], vec!["b"]]);
+        assert_eq!(labels(&pack(vec![item("big", 500)], 100, 0)), vec![vec!["big"]]);
+    }
+
+    #[test]
+    fn splits_lines_with_line_ranges() {
+        // Эвристика: 4 символа на токен, каждая строка ниже - 2 токена
+        let tokenizer = Tokenizer::approximate();
+        let content = "line 1\nline 2\nline 3\nline 4\nline 5";
+        let pieces = split_lines(content, 4, &tokenizer);
+        assert_eq!(
+            pieces,
+            vec![
+                (1, 2, "line 1\nline 2\n".to_string()),
+                (3, 4, "line 3\nline 4\n".to_string()),
+                (5, 5, "line 5".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn names_parts_after_output_file() {
+        assert_eq!(part_path(Path::new("/out/output.md"), 2), Path::new("/out/output-part-2.md"));
+        assert_eq!(part_path(Path::new("context"), 1), Path::new("context-part-1"));
+    }
+}
@@ -0,0 +1,547 @@
+use std::borrow::Cow;
+
+// Синтаксис комментариев и строковых литералов (по языку из get_language_by_extension)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Syntax {
+    Rust,
+    CLike, // C, C++, Java
+    JavaScript,
+    Go,
+    Python,
+    Shell,
+}
+
+impl Syntax {
+    pub fn for_language(language: &str) -> Option<Self> {
+        match language {
+            "rust" => Some(Self::Rust),
+            "c" | "cpp" | "java" => Some(Self::CLike),
+            "typescript" | "tsx" | "javascript" | "jsx" => Some(Self::JavaScript),
+            "go" => Some(Self::Go),
+            "python" => Some(Self::Python),
+            "bash" => Some(Self::Shell),
+            _ => None,
+        }
+    }
+
+    // Отступы значимы (Python) или могут попасть в here-doc (shell)
+    fn keeps_indentation(self) -> bool {
+        matches!(self, Self::Python | Self::Shell)
+    }
+
+    fn has_slash_comments(self) -> bool {
+        !matches!(self, Self::Python | Self::Shell)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StripOptions {
+    pub comments: bool,
+    pub blank_lines: bool, // Несколько пустых строк подряд -> одна
+    pub trailing_whitespace: bool,
+    pub indentation: bool, // Только для языков, где отступы не значимы
+}
+
+// Состояние строки после разбора: внутри литерала пробелы менять нельзя
+#[derive(Debug, Clone, Copy, Default)]
+struct LineState {
+    starts_in_literal: bool,
+    ends_in_literal: bool,
+    had_comment: bool,
+}
+
+// Удаление комментариев и лишних пробелов. Возвращает оставшиеся строки с исходными номерами (с 1)
+// или None, если файл не удалось надежно разобрать (незакрытый литерал, here-doc) - тогда он выводится как есть.
+pub fn strip<'a>(content: &'a str, syntax: Syntax, options: &StripOptions) -> Option<Vec<(usize, Cow<'a, str>)>> {
+    if syntax == Syntax::Shell && has_heredoc(content) {
+        return None;
+    }
+
+    let Lexed { code, lines: states, .. } = Lexer::new(content, syntax, options.comments).run()?;
+    // Если комментарии не удалялись, неизмененные строки берутся из `content` без копирования
+    let source = code.as_deref().unwrap_or(content);
+
+    let mut lines = Vec::new();
+    let mut previous_blank = false;
+    let mut offset = 0;
+    for (i, line) in source.split_inclusive('\n').enumerate() {
+        let start = offset;
+        offset += line.len();
+        let state = states.get(i).copied().unwrap_or_default();
+        let (body, ending) = split_line_ending(line);
+
+        let mut text = body;
+        if !state.ends_in_literal && (options.trailing_whitespace || state.had_comment) {
+            text = text.trim_end_matches([' ', '\t']);
+        }
+        if options.indentation && !syntax.keeps_indentation() && !state.starts_in_literal {
+            text = text.trim_start_matches([' ', '\t']);
+        }
+
+        let blank = text.trim().is_empty() && !state.starts_in_literal && !state.ends_in_literal;
+        // Строка, в которой был только комментарий, исчезает целиком
+        if blank && state.had_comment && options.comments {
+            continue;
+        }
+        if blank && previous_blank && options.blank_lines {
+            continue;
+        }
+        previous_blank = blank;
+
+        let unchanged = text.len() == body.len();
+        let text = match code.is_none() && unchanged {
+            true => Cow::Borrowed(&content[start..offset]),
+            false => Cow::Owned(format!("{}{}", text, ending)),
+        };
+        lines.push((i + 1, text));
+    }
+    Some(lines)
+}
+
+fn split_line_ending(line: &str) -> (&str, &str) {
+    let body = line.trim_end_matches(['\n', '\r']);
+    (body, &line[body.len()..])
+}
+
+// Содержимое here-doc не отличить от кода без полного разбора shell
+fn has_heredoc(content: &str) -> bool {
+    content.match_indices("<<").any(|(i, _)| !content[i + 2..].starts_with('<'))
+}
+
+// Для каждого байта `content`: true - код, false - комментарий или литерал.
+// None, если файл не удалось разобрать.
+pub fn code_mask(content: &str, syntax: Syntax) -> Option<Vec<bool>> {
+    Lexer::new(content, syntax, false).run().map(|lexed| lexed.mask)
+}
+
+enum Comment {
+    Line,
+    Block,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Span {
+    Code,
+    Literal,
+    Comment,
+}
+
+struct Lexed {
+    code: Option<String>, // Текст без комментариев или None, если они не удалялись
+    lines: Vec<LineState>,
+    mask: Vec<bool>,
+}
+
+fn is_ident_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+// Ключевые слова JS, после которых `/` начинает регулярное выражение, а не деление
+const JS_REGEX_KEYWORDS: &[&[u8]] = &[
+    b"return", b"typeof", b"instanceof", b"in", b"of", b"new", b"delete", b"void", b"throw", b"case", b"do",
+    b"else", b"yield", b"await",
+];
+
+// Разбор на уровне байтов: все разделители - ASCII, поэтому многобайтовые символы
+// копируются или удаляются целиком и результат остается валидным UTF-8.
+struct Lexer<'a> {
+    src: &'a [u8],
+    syntax: Syntax,
+    strip_comments: bool,
+    pos: usize,
+    out: Vec<u8>,
+    lines: Vec<LineState>,
+    mask: Vec<bool>,
+    last_token: Option<(usize, Span)>, // Последний значимый байт (не пробел и не комментарий)
+}
+
+impl<'a> Lexer<'a> {
+    fn new(content: &'a str, syntax: Syntax, strip_comments: bool) -> Self {
+        Self {
+            src: content.as_bytes(),
+            syntax,
+            strip_comments,
+            pos: 0,
+            out: Vec::with_capacity(content.len()),
+            lines: vec![LineState::default()],
+            mask: Vec::with_capacity(content.len()),
+            last_token: None,
+        }
+    }
+
+    fn run(mut self) -> Option<Lexed> {
+        while self.pos < self.src.len() {
+            match self.comment_start() {
+                Some(Comment::Line) => {
+                    self.line_comment();
+                    continue;
+                }
+                Some(Comment::Block) => {
+                    self.block_comment()?;
+                    continue;
+                }
+                None => {}
+            }
+            if self.literal()? {
+                continue;
+            }
+            self.copy(1, Span::Code);
+        }
+
+        let code = match self.strip_comments {
+            true => Some(String::from_utf8(self.out).ok()?),
+            false => None,
+        };
+        Some(Lexed {
+            code,
+            lines: self.lines,
+            mask: self.mask,
+        })
+    }
+
+    fn peek(&self, offset: usize) -> Option<u8> {
+        self.src.get(self.pos + offset).copied()
+    }
+
+    fn line(&mut self) -> &mut LineState {
+        self.lines.last_mut().expect("lines is never empty")
+    }
+
+    // Переносит `len` байт в результат; из удаляемых комментариев остаются только переводы строк
+    // (в Go и JS перевод строки внутри комментария значим для расстановки `;`)
+    fn copy(&mut self, len: usize, span: Span) {
+        let end = (self.pos + len).min(self.src.len());
+        let in_literal = span == Span::Literal;
+        for (i, &b) in self.src[self.pos..end].iter().enumerate() {
+            if self.strip_comments && (span != Span::Comment || b == b'\n') {
+                self.out.push(b);
+            }
+            if span == Span::Literal || (span == Span::Code && !b.is_ascii_whitespace()) {
+                self.last_token = Some((self.pos + i, span));
+            }
+            self.mask.push(span == Span::Code);
+            if b == b'\n' {
+                self.line().ends_in_literal = in_literal;
+                self.lines.push(LineState {
+                    starts_in_literal: in_literal,
+                    had_comment: span == Span::Comment,
+                    ..Default::default()
+                });
+            }
+        }
+        self.pos = end;
+    }
+
+    fn comment_start(&self) -> Option<Comment> {
+        let b = self.src[self.pos];
+        if self.syntax.has_slash_comments() {
+            return match (b, self.peek(1)) {
+                (b'/', Some(b'/')) => Some(Comment::Line),
+                (b'/', Some(b'*')) => Some(Comment::Block),
+                _ => None,
+            };
+        }
+        if b != b'#' || (self.pos == 0 && self.peek(1) == Some(b'!')) {
+            return None; // Shebang оставляем
+        }
+        // В shell `#` начинает комментарий только в начале слова ($#, ${#var} - не комментарии)
+        let word_start = self.pos == 0 || matches!(self.src[self.pos - 1], b' ' | b'\t' | b'\n' | b';');
+        match self.syntax {
+            Syntax::Shell if !word_start => None,
+            _ => Some(Comment::Line),
+        }
+    }
+
+    fn line_comment(&mut self) {
+        let end = self.src[self.pos..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map(|i| self.pos + i)
+            .unwrap_or(self.src.len());
+        self.skip_comment(end);
+    }
+
+    // Блочный комментарий; в Rust вложенный. Незакрытый - ошибка разбора
+    fn block_comment(&mut self) -> Option<()> {
+        let mut depth = 0;
+        let mut i = self.pos;
+        while i + 1 < self.src.len() {
+            match (self.src[i], self.src[i + 1]) {
+                (b'/', b'*') if depth == 0 || self.syntax == Syntax::Rust => {
+                    depth += 1;
+                    i += 2;
+                }
+                (b'*', b'/') => {
+                    depth -= 1;
+                    i += 2;
+                    if depth == 0 {
+                        self.skip_comment(i);
+                        return Some(());
+                    }
+                }
+                _ => i += 1,
+            }
+        }
+        None
+    }
+
+    fn skip_comment(&mut self, end: usize) {
+        self.line().had_comment = true;
+        self.copy(end - self.pos, Span::Comment);
+    }
+
+    // Строковый или символьный литерал с текущей позиции. Some(false) - литерала нет, None - он не закрыт
+    fn literal(&mut self) -> Option<bool> {
+        let b = self.src[self.pos];
+        let prev_ident = self.pos > 0 && is_ident_byte(self.src[self.pos - 1]);
+        let len = match (self.syntax, b) {
+            (Syntax::Rust, b'r') | (Syntax::Rust, b'b') if !prev_ident => match self.rust_raw_string() {
+                Some(len) => len,
+                None => return Some(false),
+            },
+            (Syntax::Rust, b'\'') => match self.rust_char() {
+                Some(len) => len,
+                None => return Some(false), // Время жизни или метка
+            },
+            (Syntax::Python, b'"') | (Syntax::Python, b'\'') if self.src[self.pos..].starts_with(&[b, b, b]) => {
+                let end = self.find_closing(3, &[b, b, b], true)?;
+                end - self.pos
+            }
+            (_, b'"') => {
+                // Многострочные только в Rust и shell (в C/C++ - через `\` перед переводом строки)
+                let multiline = matches!(self.syntax, Syntax::Rust | Syntax::Shell);
+                self.find_closing(1, b"\"", multiline)? - self.pos
+            }
+            (Syntax::Shell, b'\'') => self.find_raw_closing(1, b"'")? - self.pos,
+            (_, b'\'') => self.find_closing(1, b"'", false)? - self.pos,
+            (Syntax::JavaScript, b'`') | (Syntax::Shell, b'`') => self.find_closing(1, b"`", true)? - self.pos,
+            // `//` и `/*` уже разобраны как комментарии
+            (Syntax::JavaScript, b'/') if self.regex_allowed() => match self.js_regex_end() {
+                Some(end) => end - self.pos,
+                None => return Some(false),
+            },
+            (Syntax::Go, b'`') => self.find_raw_closing(1, b"`")? - self.pos,
+            _ => return Some(false),
+        };
+        self.copy(len, Span::Literal);
+        Some(true)
+    }
+
+    // Позиция после закрывающей `quote` с учетом `\`-экранирования.
+    // Перевод строки в однострочном литерале - ошибка разбора.
+    fn find_closing(&self, skip: usize, quote: &[u8], multiline: bool) -> Option<usize> {
+        let mut i = self.pos + skip;
+        while i < self.src.len() {
+            match self.src[i] {
+                b'\\' => i += 2,
+                b'\n' if !multiline => return None,
+                _ if self.src[i..].starts_with(quote) => return Some(i + quote.len()),
+                _ => i += 1,
+            }
+        }
+        None
+    }
+
+    // То же без экранирования (raw-строки Go, одинарные кавычки shell)
+    fn find_raw_closing(&self, skip: usize, quote: &[u8]) -> Option<usize> {
+        let start = self.pos + skip;
+        self.src[start..]
+            .windows(quote.len())
+            .position(|w| w == quote)
+            .map(|i| start + i + quote.len())
+    }
+
+    // `/` начинает регулярное выражение там, где ожидается выражение: в начале файла,
+    // после оператора, открывающей скобки, `,`, `;` или ключевого слова (`return /x/`).
+    // После имени, числа, строки или `)` / `]` - это деление.
+    fn regex_allowed(&self) -> bool {
+        let (last, span) = match self.last_token {
+            Some(token) => token,
+            None => return true,
+        };
+        if span == Span::Literal {
+            return false;
+        }
+        match self.src[last] {
+            b')' | b']' => false,
+            b if is_ident_byte(b) || b == b'$' => {
+                let start = self.src[..=last]
+                    .iter()
+                    .rposition(|&b| !(is_ident_byte(b) || b == b'$'))
+                    .map(|i| i + 1)
+                    .unwrap_or(0);
+                JS_REGEX_KEYWORDS.contains(&&self.src[start..=last])
+            }
+            _ => true,
+        }
+    }
+
+    // Позиция после закрывающего `/` регулярного выражения (`/` внутри `[...]` его не закрывает).
+    // None - до конца строки выражение не закрылось, значит это не оно.
+    fn js_regex_end(&self) -> Option<usize> {
+        let mut in_class = false;
+        let mut i = self.pos + 1;
+        while i < self.src.len() {
+            match self.src[i] {
+                b'\\' => i += 2,
+                b'\n' => return None,
+                b'[' => {
+                    in_class = true;
+                    i += 1;
+                }
+                b']' => {
+                    in_class = false;
+                    i += 1;
+                }
+                b'/' if !in_class => return Some(i + 1),
+                _ => i += 1,
+            }
+        }
+        None
+    }
+
+    // r"...", r#"..."#, br"...": длина литерала или None, если это не raw-строка
+    fn rust_raw_string(&self) -> Option<usize> {
+        let mut i = self.pos;
+        if self.src[i] == b'b' {
+            i += 1;
+        }
+        if self.src.get(i) != Some(&b'r') {
+            return None;
+        }
+        i += 1;
+        let hashes = self.src[i..].iter().take_while(|&&b| b == b'#').count();
+        i += hashes;
+        if self.src.get(i) != Some(&b'"') {
+            return None;
+        }
+        let closing = format!("\"{}", "#".repeat(hashes));
+        let end = self.src[i + 1..]
+            .windows(closing.len())
+            .position(|w| w == closing.as_bytes())?;
+        Some(i + 1 + end + closing.len() - self.pos)
+    }
+
+    // 'a', '\n', '\u{1F600}', 'ж' - символ; 'a без закрывающей кавычки - время жизни
+    fn rust_char(&self) -> Option<usize> {
+        match self.peek(1)? {
+            b'\\' => self.find_closing(1, b"'", false).map(|end| end - self.pos),
+            first => {
+                let width = match first {
+                    0x00..=0x7f => 1,
+                    0xc0..=0xdf => 2,
+                    0xe0..=0xef => 3,
+                    _ => 4,
+                };
+                (self.peek(1 + width) == Some(b'\'')).then_some(width + 2)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> StripOptions {
+        StripOptions {
+            comments: true,
+            blank_lines: true,
+            trailing_whitespace: true,
+            indentation: false,
+        }
+    }
+
+    // Текст после удаления и исходные номера строк
+    fn stripped(content: &str, syntax: Syntax) -> (String, Vec<usize>) {
+        let lines = strip(content, syntax, &options()).expect("parsed");
+        let numbers = lines.iter().map(|(n, _)| *n).collect();
+        (lines.into_iter().map(|(_, text)| text.into_owned()).collect(), numbers)
+    }
+
+    // Участки кода из маски (литералы и комментарии заменены на `_`)
+    fn masked(content: &str, syntax: Syntax) -> String {
+        let mask = code_mask(content, syntax).expect("parsed");
+        content
+            .bytes()
+            .zip(mask)
+            .map(|(b, code)| if code { b as char } else { '_' })
+            .collect()
+    }
+
+    #[test]
+    fn removes_comments_and_keeps_line_numbers() {
+        let content = "// header\nfn main() { // entry\n    let x = 1; /* one */\n}\n";
+        let (text, numbers) = stripped(content, Syntax::Rust);
+        assert_eq!(text, "fn main() {\n    let x = 1;\n}\n");
+        assert_eq!(numbers, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn keeps_comment_markers_inside_strings() {
+        let content = "let url = \"http://example.com\"; // link\nlet s = '/*';\n";
+        let (text, _) = stripped(content, Syntax::JavaScript);
+        assert_eq!(text, "let url = \"http://example.com\";\nlet s = '/*';\n");
+        assert_eq!(masked("a(\"//\")", Syntax::Go), "a(____)");
+    }
+
+    #[test]
+    fn nests_block_comments_only_in_rust() {
+        let content = "/* outer /* inner */ still */ fn f() {}\n";
+        assert_eq!(stripped(content, Syntax::Rust).0, " fn f() {}\n");
+        // В C комментарий заканчивается на первом `*/`
+        assert_eq!(stripped(content, Syntax::CLike).0, " still */ fn f() {}\n");
+    }
+
+    #[test]
+    fn rejects_unterminated_literals_and_comments() {
+        assert!(code_mask("let s = \"open;\nlet t = 1;\n", Syntax::JavaScript).is_none());
+        assert!(code_mask("int x; /* open\n", Syntax::CLike).is_none());
+        assert!(code_mask("/* a /* b */\n", Syntax::Rust).is_none());
+        assert!(code_mask("s = '''docstring\n", Syntax::Python).is_none());
+        // Многострочные строки Rust и шаблоны JS - не ошибка
+        assert!(code_mask("let s = \"a\nb\";\n", Syntax::Rust).is_some());
+        assert!(code_mask("const s = `a\nb`;\n", Syntax::JavaScript).is_some());
+    }
+
+    #[test]
+    fn distinguishes_rust_lifetimes_chars_and_raw_strings() {
+        assert_eq!(masked("fn f<'a>(x: &'a str) {}", Syntax::Rust), "fn f<'a>(x: &'a str) {}");
+        assert_eq!(masked("let c = '{'; let d = 'ж';", Syntax::Rust), "let c = ___; let d = ____;");
+        assert_eq!(masked("r#\"a \"// b\"#;", Syntax::Rust), "____________;");
+    }
+
+    #[test]
+    fn keeps_literal_lines_untouched() {
+        let content = "x = \"\"\"doc   \n\n\n   end\"\"\"   # tail\ny = 1\n";
+        let (text, numbers) = stripped(content, Syntax::Python);
+        assert_eq!(text, "x = \"\"\"doc   \n\n\n   end\"\"\"\ny = 1\n");
+        assert_eq!(numbers, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn lexes_js_regex_literals() {
+        let content = "const re = /[/*]/g; // tail\nif (/\\/\\*/.test(s)) return /a\\/b/;\nconst x = 1; /* c */\n";
+        let (text, _) = stripped(content, Syntax::JavaScript);
+        assert_eq!(
+            text,
+            "const re = /[/*]/g;\nif (/\\/\\*/.test(s)) return /a\\/b/;\nconst x = 1;\n"
+        );
+        assert_eq!(masked("x = /a b/;", Syntax::JavaScript), "x = _____;");
+    }
+
+    #[test]
+    fn keeps_js_division() {
+        assert_eq!(masked("a = b / c / d;", Syntax::JavaScript), "a = b / c / d;");
+        assert_eq!(masked("n = (a + b) / 2 / x[1] / 3;", Syntax::JavaScript), "n = (a + b) / 2 / x[1] / 3;");
+        let content = "total = sum / count; // average\nratio = \"a\" / 2 /* odd */;\n";
+        assert_eq!(stripped(content, Syntax::JavaScript).0, "total = sum / count;\nratio = \"a\" / 2 ;\n");
+    }
+
+    #[test]
+    fn shell_hash_is_a_comment_only_at_word_start() {
+        let content = "#!/bin/sh\necho $# ${#list} # count\n";
+        assert_eq!(stripped(content, Syntax::Shell).0, "#!/bin/sh\necho $# ${#list}\n");
+        assert!(strip("cat <<EOF\n# not a comment\nEOF\n", Syntax::Shell, &options()).is_none());
+    }
+}
@@ -0,0 +1,120 @@
+use crate::models::AppConfig;
+use minijinja::{AutoEscape, Environment, UndefinedBehavior, Value};
+use std::collections::HashSet;
+
+// Переменные шаблона файла (output_template)
+pub const FILE_VARS: &[&str] = &[
+    "path",
+    "name",
+    "extension",
+    "absolute_path",
+    "language",
+    "size",
+    "tokens",
+    "lines",
+    "modified",
+    "index",
+    "total",
+    "content",
+    "fence",
+    "git_commit",
+    "git_author",
+    "git_date",
+    "git_subject",
+    "git_commits",
+];
+
+// Переменные шаблонов заголовка и завершения документа
+pub const DOCUMENT_VARS: &[&str] = &["tree", "files", "part"];
+
+// Библиотека макросов доступна во всех шаблонах как `partials`
+const PARTIALS: &str = "partials";
+
+// (имя шаблона, название настройки для ошибок, допустимые переменные)
+const TEMPLATES: &[(&str, &str, &[&str])] = &[
+    (PARTIALS, "Template Partials", &[]),
+    ("header", "Header Template", DOCUMENT_VARS),
+    ("file", "Output Template", FILE_VARS),
+    ("footer", "Footer Template", DOCUMENT_VARS),
+];
+
+// Скомпилированные шаблоны markdown-вывода (синтаксис Jinja2)
+pub struct Templates {
+    env: Environment<'static>,
+    file_vars: HashSet<String>, // Переменные, которые использует шаблон файла
+}
+
+impl Templates {
+    // Компиляция с проверкой: синтаксическая ошибка или неизвестная переменная - ошибка
+    pub fn compile(config: &AppConfig) -> Result<Self, String> {
+        let mut env = Environment::new();
+        env.set_keep_trailing_newline(true); // Шаблоны по умолчанию заканчиваются переводами строк
+        env.set_auto_escape_callback(|_| AutoEscape::None);
+        env.set_undefined_behavior(UndefinedBehavior::Strict);
+
+        let import = format!("{{% import \"{}\" as {} %}}", PARTIALS, PARTIALS);
+        for (name, label, _) in TEMPLATES {
+            let source = match *name {
+                PARTIALS => config.template_partials.clone(),
+                "header" => format!("{}{}", import, config.header_template),
+                "file" => format!("{}{}", import, config.output_template),
+                _ => format!("{}{}", import, config.footer_template),
+            };
+            env.add_template_owned(name.to_string(), source)
+                .map_err(|e| format!("{}: {}", label, e))?;
+        }
+
+        // Опечатка в имени переменной не должна молча давать пустую строку
+        let globals: HashSet<String> = env.globals().map(|(name, _)| name.to_string()).collect();
+        let mut file_vars = HashSet::new();
+        for (name, label, allowed) in TEMPLATES {
+            let template = env.get_template(name).map_err(|e| format!("{}: {}", label, e))?;
+            let used = template.undeclared_variables(false);
+            let mut unknown: Vec<&String> = used
+                .iter()
+                .filter(|v| !allowed.contains(&v.as_str()) && !globals.contains(*v))
+                .collect();
+            unknown.sort();
+            if let Some(var) = unknown.first() {
+                return Err(format!("{}: unknown variable `{}`", label, var));
+            }
+            if *name == "file" {
+                file_vars = used;
+            }
+        }
+
+        Ok(Self { env, file_vars })
+    }
+
+    // Шаблоны из конфига; при ошибке - шаблоны по умолчанию
+    pub fn from_config_or_default(config: &AppConfig) -> Self {
+        Self::compile(config).unwrap_or_else(|e| {
+            log::warn!("Invalid template ({}), falling back to defaults", e);
+            Self::compile(&AppConfig::default()).expect("default templates are valid")
+        })
+    }
+
+    // Использует ли шаблон файла переменную (например, git-метаданные читаются только по необходимости)
+    pub fn file_uses(&self, var: &str) -> bool {
+        self.file_vars.contains(var)
+    }
+
+    pub fn render_header(&self, context: &Value) -> Result<String, String> {
+        self.render("header", context)
+    }
+
+    pub fn render_file(&self, context: &Value) -> Result<String, String> {
+        self.render("file", context)
+    }
+
+    pub fn render_footer(&self, context: &Value) -> Result<String, String> {
+        self.render("footer", context)
+    }
+
+    fn render(&self, name: &str, context: &Value) -> Result<String, String> {
+        self.env
+            .get_template(name)
+            .and_then(|template| template.render(context))
+            .map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,194 @@
+use crate::engine::tokens::Tokenizer;
+use crate::engine::transform::ContentTransform;
+use crate::models::{FileClass, TextEncoding};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+// Версия формата файла кэша (при изменении старые кэши отбрасываются)
+const CACHE_VERSION: u32 = 4;
+
+// Отпечаток файла: если размер и mtime совпадают, считаем содержимое неизменным
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Fingerprint {
+    size: u64,
+    mtime_secs: u64,
+    mtime_nanos: u32,
+}
+
+impl Fingerprint {
+    fn from_metadata(meta: &Metadata) -> Option<Self> {
+        let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(Self {
+            size: meta.len(),
+            mtime_secs: mtime.as_secs(),
+            mtime_nanos: mtime.subsec_nanos(),
+        })
+    }
+}
+
+// Токены файла после преобразований содержимого и без них; заодно класс и кодировка
+// файла, чтобы не читать неизмененный файл ради них заново
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenCounts {
+    pub tokens: usize,
+    pub raw_tokens: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class: Option<FileClass>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<TextEncoding>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct CacheEntry {
+    #[serde(flatten)]
+    fingerprint: Fingerprint,
+    #[serde(flatten)]
+    counts: TokenCounts,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    tokenizer: String, // Токенизатор и преобразования содержимого
+    entries: HashMap<String, CacheEntry>,
+}
+
+// Кэш количества токенов на диске: один файл на проект, ключ - путь + размер + mtime.
+// Привязан к токенизатору и преобразованиям содержимого: при их смене кэш начинается заново.
+pub struct TokenCache {
+    file: Option<PathBuf>, // None - только в памяти (нет каталога кэша)
+    tokenizer: String,
+    entries: Mutex<HashMap<String, CacheEntry>>,
+    dirty: AtomicBool,
+}
+
+impl TokenCache {
+    // Загрузка кэша проекта из системного каталога кэша; при любой ошибке - пустой кэш
+    pub fn open(root: &Path, tokenizer: &Tokenizer, transform: &ContentTransform) -> Self {
+        let tokenizer = format!("{}{}", tokenizer.cache_key(), transform.cache_key());
+        let file = cache_file_path(root, &tokenizer);
+
+        let entries = file
+            .as_ref()
+            .and_then(|f| fs::read(f).ok())
+            .and_then(|bytes| serde_json::from_slice::<CacheFile>(&bytes).ok())
+            .filter(|c| c.version == CACHE_VERSION && c.tokenizer == tokenizer)
+            .map(|c| c.entries)
+            .unwrap_or_default();
+        log::debug!("Loaded {} cached token counts for {}", entries.len(), root.display());
+
+        Self {
+            file,
+            tokenizer,
+            entries: Mutex::new(entries),
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    // Количество токенов, если файл не менялся с момента подсчета
+    pub fn get(&self, path: &str, meta: &Metadata) -> Option<TokenCounts> {
+        let fingerprint = Fingerprint::from_metadata(meta)?;
+        let entries = self.entries.lock().ok()?;
+        entries
+            .get(path)
+            .filter(|e| e.fingerprint == fingerprint)
+            .map(|e| e.counts)
+    }
+
+    pub fn insert(&self, path: &str, meta: &Metadata, counts: TokenCounts) {
+        let fingerprint = match Fingerprint::from_metadata(meta) {
+            Some(f) => f,
+            None => return,
+        };
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(path.to_string(), CacheEntry { fingerprint, counts });
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+    }
+
+    // Удаляет записи файлов, которых больше нет в проекте
+    pub fn retain(&self, paths: &HashSet<String>) {
+        if let Ok(mut entries) = self.entries.lock() {
+            let before = entries.len();
+            entries.retain(|path, _| paths.contains(path));
+            if entries.len() != before {
+                self.dirty.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    // Запись на диск (только если были изменения)
+    pub fn save(&self) -> Result<(), String> {
+        let file = match &self.file {
+            Some(f) => f,
+            None => return Ok(()),
+        };
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let content = {
+            let entries = self.entries.lock().map_err(|_| "Failed to lock token cache")?;
+            serde_json::to_vec(&CacheFile {
+                version: CACHE_VERSION,
+                tokenizer: self.tokenizer.clone(),
+                entries: entries.clone(),
+            })
+            .map_err(|e| format!("Failed to serialize token cache: {}", e))?
+        };
+
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create cache dir {}: {}", dir.display(), e))?;
+        }
+        // Пишем во временный файл и переименовываем, чтобы не оставить битый кэш
+        let tmp = file.with_extension("json.tmp");
+        fs::write(&tmp, content)
+            .and_then(|_| fs::rename(&tmp, file))
+            .map_err(|e| format!("Failed to write token cache {}: {}", file.display(), e))
+    }
+}
+
+// <cache_dir>/repo2txt/tokens/<хэш корня и токенизатора>.json. Хэш должен быть одинаковым
+// в разных сборках (DefaultHasher это не гарантирует), иначе после обновления кэш теряется
+fn cache_file_path(root: &Path, tokenizer: &str) -> Option<PathBuf> {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    dirs::cache_dir().map(|dir| dir.join("repo2txt").join("tokens").join(cache_file_name(&root, tokenizer)))
+}
+
+fn cache_file_name(root: &Path, tokenizer: &str) -> String {
+    let key = [root.as_os_str().as_encoded_bytes(), &[0], tokenizer.as_bytes()].concat();
+    format!("{:016x}.json", fnv1a(&key))
+}
+
+// FNV-1a, 64 бита
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fnv1a_matches_reference_values() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn cache_file_name_depends_on_root_and_tokenizer() {
+        let name = cache_file_name(Path::new("/home/user/project"), "o200k_base");
+        assert_eq!(name, format!("{:016x}.json", fnv1a(b"/home/user/project\0o200k_base")));
+        assert_ne!(name, cache_file_name(Path::new("/home/user/project"), "cl100k_base"));
+        assert_ne!(name, cache_file_name(Path::new("/home/user/other"), "o200k_base"));
+    }
+}
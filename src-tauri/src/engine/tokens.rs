@@ -0,0 +1,166 @@
+use crate::models::AppConfig;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::UNIX_EPOCH;
+use tiktoken_rs::{cl100k_base, o200k_base, p50k_base, CoreBPE};
+
+// Способ подсчета токенов (настройка `tokenizer` в AppConfig)
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenizerKind {
+    Cl100k,               // GPT-4, GPT-3.5
+    O200k,                // GPT-4o и новее
+    P50k,                 // Codex, text-davinci
+    Chars(f64),           // Эвристика: N символов на токен
+    HuggingFace(PathBuf), // Локальный tokenizer.json
+}
+
+impl TokenizerKind {
+    pub fn from_config(config: &AppConfig) -> Result<Self, String> {
+        match config.tokenizer.as_str() {
+            "cl100k" => Ok(Self::Cl100k),
+            "o200k" => Ok(Self::O200k),
+            "p50k" => Ok(Self::P50k),
+            "chars" => {
+                let ratio = config.chars_per_token;
+                if !ratio.is_finite() || ratio <= 0.0 {
+                    return Err(format!("Invalid characters per token: {}", ratio));
+                }
+                Ok(Self::Chars(ratio))
+            }
+            "huggingface" => {
+                let path = config.tokenizer_path.trim();
+                if path.is_empty() {
+                    return Err("Tokenizer file path is not set".to_string());
+                }
+                Ok(Self::HuggingFace(PathBuf::from(path)))
+            }
+            other => Err(format!("Unknown tokenizer: {}", other)),
+        }
+    }
+
+    // Идентификатор для кэша токенов: разные токенизаторы дают разные числа.
+    // Для tokenizer.json учитываются размер и mtime: файл по тому же пути могли заменить.
+    pub fn cache_key(&self) -> String {
+        match self {
+            Self::Cl100k => "cl100k".to_string(),
+            Self::O200k => "o200k".to_string(),
+            Self::P50k => "p50k".to_string(),
+            Self::Chars(ratio) => format!("chars:{}", ratio),
+            Self::HuggingFace(path) => {
+                let stamp = fs::metadata(path)
+                    .ok()
+                    .and_then(|meta| Some((meta.len(), meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?)))
+                    .map(|(size, mtime)| format!(":{}:{}.{}", size, mtime.as_secs(), mtime.subsec_nanos()))
+                    .unwrap_or_default();
+                format!("huggingface:{}{}", path.display(), stamp)
+            }
+        }
+    }
+}
+
+// Загруженные за время работы процесса токенизаторы (обычно один)
+static LOADED: OnceLock<Mutex<Vec<Arc<Tokenizer>>>> = OnceLock::new();
+
+enum Backend {
+    Bpe(CoreBPE),
+    Chars(f64),
+    HuggingFace(Box<tokenizers::Tokenizer>),
+}
+
+// Загруженный токенизатор. Построение BPE-таблиц дорогое - используйте `shared`.
+pub struct Tokenizer {
+    kind: TokenizerKind,
+    cache_key: String, // Ключ на момент загрузки (tokenizer.json мог измениться после)
+    backend: Backend,
+}
+
+// Примерно столько символов приходится на токен в эвристике
+const APPROXIMATE_CHARS_PER_TOKEN: f64 = 4.0;
+
+fn count_chars(content: &str, ratio: f64) -> usize {
+    (content.chars().count() as f64 / ratio).ceil() as usize
+}
+
+impl Tokenizer {
+    pub fn load(kind: TokenizerKind) -> Result<Self, String> {
+        let cache_key = kind.cache_key();
+        let backend = match &kind {
+            TokenizerKind::Cl100k => Backend::Bpe(cl100k_base().map_err(|e| e.to_string())?),
+            TokenizerKind::O200k => Backend::Bpe(o200k_base().map_err(|e| e.to_string())?),
+            TokenizerKind::P50k => Backend::Bpe(p50k_base().map_err(|e| e.to_string())?),
+            TokenizerKind::Chars(ratio) => Backend::Chars(*ratio),
+            TokenizerKind::HuggingFace(path) => {
+                let tokenizer = tokenizers::Tokenizer::from_file(path).map_err(|e| {
+                    format!("Failed to load tokenizer {}: {}", path.display(), e)
+                })?;
+                Backend::HuggingFace(Box::new(tokenizer))
+            }
+        };
+        Ok(Self {
+            kind,
+            cache_key,
+            backend,
+        })
+    }
+
+    // Общий на процесс экземпляр: таблицы BPE строятся один раз.
+    // Измененный tokenizer.json загружается заново.
+    pub fn shared(kind: TokenizerKind) -> Result<Arc<Self>, String> {
+        let loaded = LOADED.get_or_init(|| Mutex::new(Vec::new()));
+        let mut loaded = loaded.lock().map_err(|_| "Failed to lock tokenizers")?;
+        let cache_key = kind.cache_key();
+        if let Some(tokenizer) = loaded.iter().find(|t| t.cache_key == cache_key) {
+            return Ok(tokenizer.clone());
+        }
+
+        log::info!("Loading tokenizer {:?}", kind);
+        let tokenizer = Arc::new(Self::load(kind)?);
+        loaded.retain(|t| t.kind != tokenizer.kind);
+        loaded.push(tokenizer.clone());
+        Ok(tokenizer)
+    }
+
+    pub fn from_config(config: &AppConfig) -> Result<Arc<Self>, String> {
+        Self::shared(TokenizerKind::from_config(config)?)
+    }
+
+    // Токенизатор из конфига; при ошибке - эвристика (примерно 4 символа на токен)
+    pub fn from_config_or_fallback(config: &AppConfig) -> Arc<Self> {
+        Self::from_config(config).unwrap_or_else(|e| {
+            log::warn!("{}, falling back to character heuristic", e);
+            Arc::new(Self::approximate())
+        })
+    }
+
+    pub fn approximate() -> Self {
+        let kind = TokenizerKind::Chars(APPROXIMATE_CHARS_PER_TOKEN);
+        Self {
+            cache_key: kind.cache_key(),
+            kind,
+            backend: Backend::Chars(APPROXIMATE_CHARS_PER_TOKEN),
+        }
+    }
+
+    pub fn kind(&self) -> &TokenizerKind {
+        &self.kind
+    }
+
+    pub fn cache_key(&self) -> &str {
+        &self.cache_key
+    }
+
+    pub fn count(&self, content: &str) -> usize {
+        match &self.backend {
+            Backend::Bpe(bpe) => bpe.encode_with_special_tokens(content).len(),
+            Backend::Chars(ratio) => count_chars(content, *ratio),
+            Backend::HuggingFace(tokenizer) => match tokenizer.encode(content, false) {
+                Ok(encoding) => encoding.len(),
+                Err(e) => {
+                    log::warn!("Tokenizer failed, using approximate count: {}", e);
+                    count_chars(content, APPROXIMATE_CHARS_PER_TOKEN)
+                }
+            },
+        }
+    }
+}
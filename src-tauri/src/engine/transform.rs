@@ -0,0 +1,212 @@
+use crate::engine::skeleton::{self, Skeleton};
+use crate::engine::strip::{self, StripOptions, Syntax};
+use crate::models::AppConfig;
+use std::borrow::Cow;
+
+// Нумерация строк содержимого: "  12 | код"
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineNumbers {
+    pub width: usize, // 0 - по количеству цифр в номере последней строки
+    pub separator: String,
+}
+
+impl LineNumbers {
+    // Строки с исходными номерами: после удаления комментариев номера совпадают с файлом
+    fn apply(&self, lines: &[(usize, Cow<str>)], last_line: usize) -> String {
+        let width = match self.width {
+            0 => last_line.to_string().len(),
+            width => width,
+        };
+
+        let capacity = lines.iter().map(|(_, line)| line.len() + width + self.separator.len()).sum();
+        let mut numbered = String::with_capacity(capacity);
+        for (number, line) in lines {
+            numbered.push_str(&format!("{:>width$}{}", number, self.separator, width = width));
+            numbered.push_str(line);
+        }
+        numbered
+    }
+}
+
+// Удаление комментариев и лишних пробелов для выбранных языков
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeStripping {
+    pub options: StripOptions,
+    pub languages: Vec<String>, // Языки из get_language_by_extension
+}
+
+impl CodeStripping {
+    fn apply<'a>(&self, content: &'a str, language: &str) -> Option<Vec<(usize, Cow<'a, str>)>> {
+        if !self.languages.iter().any(|l| l == language) {
+            return None;
+        }
+        strip::strip(content, Syntax::for_language(language)?, &self.options)
+    }
+}
+
+// Преобразования текста файла перед выводом. Токены (в дереве, статистике и кэше)
+// считаются по уже преобразованному тексту - ровно тому, что попадет в документ.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContentTransform {
+    pub line_numbers: Option<LineNumbers>,
+    pub stripping: Option<CodeStripping>,
+}
+
+impl ContentTransform {
+    pub fn from_config(config: &AppConfig) -> Self {
+        let line_numbers = config.line_numbers.then(|| LineNumbers {
+            width: config.line_number_width,
+            separator: config.line_number_separator.clone(),
+        });
+        let options = StripOptions {
+            comments: config.strip_comments,
+            blank_lines: config.collapse_blank_lines,
+            trailing_whitespace: config.trim_trailing_whitespace,
+            indentation: config.strip_indentation,
+        };
+        let enabled = options.comments || options.blank_lines || options.trailing_whitespace || options.indentation;
+        let stripping = enabled.then(|| CodeStripping {
+            options,
+            languages: config.strip_languages.clone(),
+        });
+        Self { line_numbers, stripping }
+    }
+
+    // Без преобразований токены "до" и "после" совпадают
+    pub fn is_identity(&self) -> bool {
+        self.line_numbers.is_none() && self.stripping.is_none()
+    }
+
+    // `language` - результат get_language_by_extension
+    pub fn apply<'a>(&self, content: &'a str, language: &str) -> Cow<'a, str> {
+        self.apply_mapped(content, language).0
+    }
+
+    // То же и исходный номер (с 1) каждой строки результата;
+    // None, если строки результата совпадают со строками файла
+    pub fn apply_mapped<'a>(&self, content: &'a str, language: &str) -> (Cow<'a, str>, Option<Vec<usize>>) {
+        let stripped = self.stripping.as_ref().and_then(|s| s.apply(content, language));
+        if stripped.is_none() && self.line_numbers.is_none() {
+            return (Cow::Borrowed(content), None);
+        }
+
+        let line_numbers = stripped
+            .as_ref()
+            .map(|lines| lines.iter().map(|(n, _)| *n).collect());
+        let lines = stripped.unwrap_or_else(|| {
+            content
+                .split_inclusive('\n')
+                .enumerate()
+                .map(|(i, line)| (i + 1, Cow::Borrowed(line)))
+                .collect()
+        });
+        let text = self.render(&lines, content.split_inclusive('\n').count());
+        (Cow::Owned(text), line_numbers)
+    }
+
+    // Скелет файла (сигнатуры без тел функций) с остальными преобразованиями поверх
+    // и исходный номер каждой его строки. None - для языка скелет не строится, нужен обычный `apply`.
+    pub fn apply_skeleton(&self, content: &str, language: &str) -> Option<(String, Vec<usize>)> {
+        let Skeleton { text, line_numbers } = skeleton::skeleton(content, language)?;
+        // Скелет обещает оставить doc-комментарии, поэтому комментарии в нем не удаляются -
+        // только пробелы и пустые строки
+        let stripping = self.stripping.as_ref().map(|s| CodeStripping {
+            options: StripOptions {
+                comments: false,
+                ..s.options.clone()
+            },
+            languages: s.languages.clone(),
+        });
+        // Номера строк скелета переводятся обратно в номера строк файла
+        let lines: Vec<(usize, Cow<str>)> = match stripping.as_ref().and_then(|s| s.apply(&text, language)) {
+            Some(lines) => lines.into_iter().map(|(n, line)| (line_numbers[n - 1], line)).collect(),
+            None => text
+                .split_inclusive('\n')
+                .zip(&line_numbers)
+                .map(|(line, &n)| (n, Cow::Borrowed(line)))
+                .collect(),
+        };
+        let numbers = lines.iter().map(|(n, _)| *n).collect();
+        Some((self.render(&lines, content.split_inclusive('\n').count()), numbers))
+    }
+
+    fn render(&self, lines: &[(usize, Cow<str>)], last_line: usize) -> String {
+        match &self.line_numbers {
+            Some(numbers) => numbers.apply(lines, last_line),
+            None => lines.iter().map(|(_, line)| line.as_ref()).collect(),
+        }
+    }
+
+    // Часть ключа кэша токенов: разные преобразования дают разные числа
+    pub fn cache_key(&self) -> String {
+        let mut key = String::new();
+        if let Some(numbers) = &self.line_numbers {
+            key.push_str(&format!("+lines:{}:{:?}", numbers.width, numbers.separator));
+        }
+        if let Some(stripping) = &self.stripping {
+            let o = &stripping.options;
+            key.push_str(&format!(
+                "+strip:{}{}{}{}:{}",
+                o.comments as u8,
+                o.blank_lines as u8,
+                o.trailing_whitespace as u8,
+                o.indentation as u8,
+                stripping.languages.join(",")
+            ));
+        }
+        key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stripping() -> ContentTransform {
+        ContentTransform {
+            line_numbers: None,
+            stripping: Some(CodeStripping {
+                options: StripOptions {
+                    comments: true,
+                    blank_lines: true,
+                    trailing_whitespace: true,
+                    indentation: false,
+                },
+                languages: vec!["rust".to_string()],
+            }),
+        }
+    }
+
+    #[test]
+    fn maps_stripped_lines_to_file_lines() {
+        let content = "// header\nfn a() {}\n\n\n// b\nfn b() {}\n";
+        let (text, lines) = stripping().apply_mapped(content, "rust");
+        assert_eq!(text, "fn a() {}\n\nfn b() {}\n");
+        assert_eq!(lines, Some(vec![2, 3, 6]));
+        // Другой язык не преобразуется - строки совпадают с файлом
+        assert_eq!(stripping().apply_mapped(content, "go"), (Cow::Borrowed(content), None));
+    }
+
+    #[test]
+    fn maps_skeleton_lines_to_file_lines() {
+        let transform = ContentTransform {
+            line_numbers: Some(LineNumbers {
+                width: 0,
+                separator: ": ".to_string(),
+            }),
+            stripping: None,
+        };
+        let content = "fn a() {\n    1\n}\nfn b() {}\n";
+        let (text, lines) = transform.apply_skeleton(content, "rust").unwrap();
+        assert_eq!(text, "1: fn a() { ... }\n4: fn b() {}\n");
+        assert_eq!(lines, vec![1, 4]);
+    }
+
+    #[test]
+    fn keeps_doc_comments_when_stripping_skeletons() {
+        let content = "/// Adds one\nfn inc(x: u32) -> u32 {\n    // body\n    x + 1\n}\n\n\n\n/* Block */\nfn b() {}   \n";
+        let (text, lines) = stripping().apply_skeleton(content, "rust").unwrap();
+        assert_eq!(text, "/// Adds one\nfn inc(x: u32) -> u32 { ... }\n\n/* Block */\nfn b() {}\n");
+        assert_eq!(lines, vec![1, 2, 6, 9, 10]);
+    }
+}
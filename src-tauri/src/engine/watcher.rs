@@ -0,0 +1,133 @@
+use crate::engine::scanner::Scanner;
+use crate::engine::selection::Selection;
+use crate::models::TreeDelta;
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// Задержка для склейки пачки событий ФС (сохранение файла в редакторе дает несколько событий)
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(300);
+
+// Отслеживание изменений в корне проекта. Наблюдение прекращается при drop.
+pub struct RepoWatcher {
+    _debouncer: Debouncer<RecommendedWatcher>,
+}
+
+impl RepoWatcher {
+    // `on_change` вызывается из потока наблюдателя с пачкой измененных путей
+    pub fn start<F>(root: &Path, mut on_change: F) -> Result<Self, String>
+    where
+        F: FnMut(Vec<PathBuf>) + Send + 'static,
+    {
+        let mut debouncer = new_debouncer(DEBOUNCE_TIMEOUT, move |res: DebounceEventResult| {
+            match res {
+                Ok(events) => {
+                    let paths: Vec<PathBuf> = events.into_iter().map(|e| e.path).collect();
+                    if !paths.is_empty() {
+                        on_change(paths);
+                    }
+                }
+                Err(e) => log::warn!("File watcher error: {}", e),
+            }
+        })
+        .map_err(|e| format!("Failed to create file watcher: {}", e))?;
+
+        debouncer
+            .watcher()
+            .watch(root, RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch {}: {}", root.display(), e))?;
+
+        log::info!("Watching {} for changes", root.display());
+        Ok(Self {
+            _debouncer: debouncer,
+        })
+    }
+}
+
+// Применение пачки изменений ФС к выбору: добавление, удаление и изменение узлов.
+// Состояние выбора нетронутых файлов сохраняется; новые узлы наследуют выбор родителя.
+pub fn apply_changes(scanner: &Scanner, selection: &mut Selection, paths: &[PathBuf]) -> TreeDelta {
+    let mut delta = TreeDelta::default();
+
+    // На macOS события приходят с каноническими путями (/private/var вместо /var)
+    let canonical_root = scanner.root().canonicalize().ok();
+
+    // Уникальные относительные пути; BTreeSet дает порядок "родитель раньше детей"
+    let relative_paths: BTreeSet<String> = paths
+        .iter()
+        .filter_map(|p| {
+            p.strip_prefix(scanner.root())
+                .ok()
+                .or_else(|| canonical_root.as_ref().and_then(|r| p.strip_prefix(r).ok()))
+        })
+        .map(|p| p.to_string_lossy().to_string())
+        .filter(|p| !p.is_empty())
+        .collect();
+
+    for relative_path in relative_paths {
+        let full_path = scanner.root().join(&relative_path);
+
+        if !full_path.exists() {
+            // Удаление (или первая половина переименования): узел и все вложенные
+            if selection.remove(&relative_path).is_some() {
+                let prefix = format!("{}/", relative_path);
+                let nested: Vec<String> = selection
+                    .nodes()
+                    .keys()
+                    .filter(|k| k.starts_with(&prefix))
+                    .cloned()
+                    .collect();
+                for id in &nested {
+                    selection.remove(id);
+                }
+                delta.removed.push(relative_path);
+                delta.removed.extend(nested);
+            }
+            continue;
+        }
+
+        if let Some(node) = selection.get_mut(&relative_path) {
+            // Изменение содержимого: сбрасываем размер и токены до повторного анализа
+            if !node.is_directory {
+                node.size = None;
+                node.token_count = None;
+                delta.modified.push(relative_path);
+            }
+            continue;
+        }
+
+        // Пути внутри игнорируемых папок (.git, node_modules...) отбрасываем сразу
+        if scanner.is_excluded(&relative_path, full_path.is_dir()) {
+            continue;
+        }
+
+        // Новый путь: добавляем начиная с самого верхнего отсутствующего предка
+        let mut top = relative_path.clone();
+        while let Some(parent) = Path::new(&top).parent().map(|p| p.to_string_lossy().to_string()) {
+            if parent.is_empty() || selection.contains(&parent) {
+                break;
+            }
+            top = parent;
+        }
+
+        let (inherited, skeleton) = Path::new(&top)
+            .parent()
+            .and_then(|p| selection.get(&p.to_string_lossy()))
+            .map(|p| (p.selected, p.skeleton))
+            .unwrap_or((true, false));
+
+        for mut node in scanner.scan_subtree(&top) {
+            if selection.contains(&node.id) {
+                continue;
+            }
+            node.selected = inherited;
+            node.skeleton = skeleton;
+            selection.insert(node.clone());
+            delta.added.push(node);
+        }
+    }
+
+    delta
+}
@@ -1,4 +1,6 @@
+pub mod cli;
 pub mod commands;
+pub mod engine;
 pub mod models;
 pub mod state;
 
@@ -23,13 +25,21 @@ pub fn run() {
             commands::repository::get_parent_directory,
             commands::repository::open_directory,
             commands::repository::update_selection,
+            commands::repository::update_skeleton,
             commands::repository::toggle_expanded,
             commands::repository::get_tree,
             commands::repository::get_state,
             commands::repository::scan_directory,
             commands::repository::search_nodes,
+            commands::repository::search_content,
+            commands::repository::list_private_files,
+            commands::repository::select_by_content,
             commands::repository::select_all,
             commands::repository::deselect_all,
+            commands::repository::auto_select,
+            commands::git::select_changed_files,
+            commands::watcher::start_watching,
+            commands::watcher::stop_watching,
             commands::generator::generate_markdown,
             commands::generator::get_stats,
             commands::generator::copy_from_cache_to_clipboard,
@@ -38,6 +48,7 @@ pub fn run() {
             commands::file_ops::load_config,
             commands::file_ops::get_config_schema,
             commands::file_ops::get_default_config,
+            commands::file_ops::validate_config,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
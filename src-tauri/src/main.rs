@@ -2,5 +2,34 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    // С аргументами работаем как CLI (без окна), иначе запускаем GUI
+    if repo2txt_lib::cli::should_run(&args) {
+        attach_parent_console();
+        std::process::exit(repo2txt_lib::cli::run(args));
+    }
+
     repo2txt_lib::run()
 }
+
+// GUI-подсистема Windows не получает консоль: подключаемся к консоли терминала,
+// из которого запущен CLI, чтобы stdout/stderr попадали в нее.
+// Перенаправленные потоки (`> file`, `| clip`) не затрагиваются
+#[cfg(windows)]
+fn attach_parent_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+
+    // Ошибка означает, что консоли у родителя нет (запуск из проводника) - это не страшно
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_parent_console() {}
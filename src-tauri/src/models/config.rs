@@ -1,3 +1,4 @@
+use crate::engine::OutputFormat;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
@@ -6,16 +7,94 @@ pub struct AppConfig {
     pub ignored_names: HashSet<String>, // Имена файлов для игнора
     pub ignored_folders: HashSet<String>, // Имена папок для игнора
     pub binary_extensions: HashSet<String>, // Расширения (png, exe)
+    #[serde(default)]
+    pub ignore_patterns: Vec<String>, // Шаблоны в синтаксисе .gitignore (`*.generated.ts`, `docs/**/*.png`)
+    #[serde(default)]
+    pub include_patterns: Vec<String>, // Если заданы - только совпавшие файлы, игнор на них не действует
+    #[serde(default = "default_private_patterns")]
+    pub private_patterns: Vec<String>, // Приватные файлы (ключи, .env); скрываются всегда, даже с include
+    #[serde(default = "default_private_allow")]
+    pub private_allow: Vec<String>, // Исключения из private_patterns (`.env.example`)
+    #[serde(default = "default_deselect_generated")]
+    pub deselect_generated: bool, // Новые сгенерированные и минифицированные файлы не выбираются
     #[serde(default = "default_token_limit")]
     pub token_limit: usize, // Лимит токенов для визуального предупреждения
     #[serde(default = "default_max_file_size")]
     pub max_file_size: u64, // Максимальный размер файла для включения в генерацию (в байтах)
+    #[serde(default)]
+    pub auto_select_pinned: Vec<String>, // Пути (файлы и папки), которые автовыбор берет первыми
+    #[serde(default = "default_auto_select_priorities")]
+    pub auto_select_priorities: Vec<String>, // "shallow", "small", "recent" - в порядке важности
+    #[serde(default = "default_auto_select_exclude_tests")]
+    pub auto_select_exclude_tests: bool, // Автовыбор пропускает тесты (кроме закрепленных)
+    #[serde(default)]
+    pub output_format: OutputFormat, // Формат документа: "markdown" (по output_template), "xml", "json", "jsonl" или "html"
     #[serde(default = "default_output_template")]
-    pub output_template: String, // Шаблон для генерации вывода
+    pub output_template: String, // Шаблон файла (Jinja2)
+    #[serde(default = "default_header_template")]
+    pub header_template: String, // Шаблон заголовка документа (дерево файлов)
+    #[serde(default)]
+    pub footer_template: String, // Шаблон в конце документа
+    #[serde(default)]
+    pub template_partials: String, // Общие макросы, доступны в шаблонах как `partials`
+    #[serde(default)]
+    pub line_numbers: bool, // Номера строк перед каждой строкой содержимого
+    #[serde(default)]
+    pub line_number_width: usize, // Ширина номера; 0 - по номеру последней строки
+    #[serde(default = "default_line_number_separator")]
+    pub line_number_separator: String, // Между номером и строкой
+    #[serde(default)]
+    pub strip_comments: bool, // Удалять комментарии (для языков из strip_languages)
+    #[serde(default)]
+    pub collapse_blank_lines: bool, // Несколько пустых строк подряд -> одна
+    #[serde(default)]
+    pub trim_trailing_whitespace: bool,
+    #[serde(default)]
+    pub strip_indentation: bool, // Кроме языков со значимыми отступами (Python, shell)
+    #[serde(default = "default_strip_languages")]
+    pub strip_languages: Vec<String>, // Языки, к которым применяются strip_* и collapse_blank_lines
+    #[serde(default)]
+    pub skeleton_mode: bool, // Все файлы как скелет (иначе только отмеченные в дереве)
+    #[serde(default = "default_redact_secrets")]
+    pub redact_secrets: bool, // Заменять найденные секреты на [REDACTED:вид]
+    #[serde(default)]
+    pub secret_patterns: Vec<String>, // Дополнительные регулярные выражения (группа 1 - только значение)
+    #[serde(default = "default_secret_entropy_threshold")]
+    pub secret_entropy_threshold: f64, // Бит на символ для "случайных" строк; 0 - не проверять
     #[serde(default = "default_theme")]
     pub theme: String, // Тема интерфейса: "system", "light", "dark"
     #[serde(default = "default_output_filename")]
     pub output_filename: String, // Имя выходного файла по умолчанию
+    #[serde(default)]
+    pub include_git_diff: bool, // Добавлять раздел "Changes" с git diff выбранных файлов
+    #[serde(default)]
+    pub git_diff_base: String, // Ref для diff (ветка, тег, коммит); пусто - сравнение с индексом
+    #[serde(default = "default_git_diff_context")]
+    pub git_diff_context: u32, // Количество строк контекста в diff
+    #[serde(default)]
+    pub split_output: bool, // Разбивать вывод на части output-part-N.md
+    #[serde(default = "default_split_token_budget")]
+    pub split_token_budget: usize, // Максимум токенов в одной части
+    #[serde(default = "default_tokenizer")]
+    pub tokenizer: String, // Токенизатор: "cl100k", "o200k", "p50k", "chars", "huggingface"
+    #[serde(default)]
+    pub tokenizer_path: String, // Путь к tokenizer.json (для "huggingface")
+    #[serde(default = "default_chars_per_token")]
+    pub chars_per_token: f64, // Символов на токен (для "chars")
+}
+
+fn default_private_patterns() -> Vec<String> {
+    [".env", ".env.*", "*.secret", "*.key", "*.pem", "*id_rsa*", "*secrets*"]
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+fn default_private_allow() -> Vec<String> {
+    [".env.example", ".env.sample", ".env.template"]
+        .into_iter()
+        .map(String::from)
+        .collect()
 }
 
 fn default_token_limit() -> usize {
@@ -26,8 +105,49 @@ fn default_max_file_size() -> u64 {
     1024 * 1024 // 1 MB
 }
 
+fn default_auto_select_priorities() -> Vec<String> {
+    vec!["shallow".to_string(), "small".to_string()]
+}
+
+fn default_auto_select_exclude_tests() -> bool {
+    true
+}
+
+fn default_deselect_generated() -> bool {
+    true
+}
+
 fn default_output_template() -> String {
-    "## {{path}}\n\n```{{language}}\n{{content}}\n```\n\n---\n\n".to_string()
+    "## {{path}}\n\n{{fence}}{{language}}\n{{content}}\n{{fence}}\n\n---\n\n".to_string()
+}
+
+fn default_header_template() -> String {
+    concat!(
+        "# Collected Files{% if part %} (part {{ part.index }} of {{ part.total }}){% endif %}\n\n",
+        "{% if tree is not none %}## File Structure\n\n```\n{{ tree }}\n```\n",
+        "{% else %}## Files in This Part\n\n{% for file in files %}- {{ file }}\n{% endfor %}{% endif %}",
+        "\n---\n\n",
+    )
+    .to_string()
+}
+
+fn default_line_number_separator() -> String {
+    " | ".to_string()
+}
+
+fn default_strip_languages() -> Vec<String> {
+    vec!["rust", "typescript", "tsx", "javascript", "jsx", "python", "go", "c", "cpp", "java", "bash"]
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+fn default_redact_secrets() -> bool {
+    false
+}
+
+fn default_secret_entropy_threshold() -> f64 {
+    4.5
 }
 
 fn default_theme() -> String {
@@ -38,6 +158,22 @@ fn default_output_filename() -> String {
     "output.md".to_string()
 }
 
+fn default_git_diff_context() -> u32 {
+    3
+}
+
+fn default_split_token_budget() -> usize {
+    100000
+}
+
+fn default_tokenizer() -> String {
+    "cl100k".to_string()
+}
+
+fn default_chars_per_token() -> f64 {
+    4.0
+}
+
 impl Default for AppConfig {
     fn default() -> Self {
         // Игнорируемые файлы (не папки)
@@ -194,11 +330,43 @@ impl Default for AppConfig {
             ignored_names: ignored_files.into_iter().map(String::from).collect(),
             ignored_folders: ignored_folders.into_iter().map(String::from).collect(),
             binary_extensions: binary.into_iter().map(String::from).collect(),
+            ignore_patterns: Vec::new(),
+            include_patterns: Vec::new(),
+            private_patterns: default_private_patterns(),
+            private_allow: default_private_allow(),
+            deselect_generated: default_deselect_generated(),
             token_limit: default_token_limit(),
             max_file_size: default_max_file_size(),
+            auto_select_pinned: Vec::new(),
+            auto_select_priorities: default_auto_select_priorities(),
+            auto_select_exclude_tests: default_auto_select_exclude_tests(),
+            output_format: OutputFormat::default(),
             output_template: default_output_template(),
+            header_template: default_header_template(),
+            footer_template: String::new(),
+            template_partials: String::new(),
+            line_numbers: false,
+            line_number_width: 0,
+            line_number_separator: default_line_number_separator(),
+            strip_comments: false,
+            collapse_blank_lines: false,
+            trim_trailing_whitespace: false,
+            strip_indentation: false,
+            strip_languages: default_strip_languages(),
+            skeleton_mode: false,
+            redact_secrets: default_redact_secrets(),
+            secret_patterns: Vec::new(),
+            secret_entropy_threshold: default_secret_entropy_threshold(),
             theme: default_theme(),
             output_filename: default_output_filename(),
+            include_git_diff: false,
+            git_diff_base: String::new(),
+            git_diff_context: default_git_diff_context(),
+            split_output: false,
+            split_token_budget: default_split_token_budget(),
+            tokenizer: default_tokenizer(),
+            tokenizer_path: String::new(),
+            chars_per_token: default_chars_per_token(),
         }
     }
 }
@@ -17,6 +17,35 @@ pub struct FileNode {
     pub token_count: Option<usize>, // Вычисляется лениво или в фоне
     pub selected: bool,
     pub expanded: bool,
+    #[serde(default)]
+    pub skeleton: bool, // Выводить только сигнатуры (тела функций скрыты)
+    #[serde(default)]
+    pub class: Option<FileClass>, // Определяется фоновым анализом
+    #[serde(default)]
+    pub encoding: Option<TextEncoding>, // Исходная кодировка (текст всегда выводится в UTF-8)
+}
+
+// Файлы, которые обычно только тратят токены
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileClass {
+    Generated, // Вывод кодогенератора: метка в заголовке или известный суффикс
+    Minified,  // `.min.js`, сборки и файлы с очень длинными строками
+}
+
+// Кодировка текстового файла, определенная по BOM и содержимому
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextEncoding {
+    #[serde(rename = "utf-8")]
+    Utf8,
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+    #[serde(rename = "utf-16be")]
+    Utf16Be,
+    #[serde(rename = "windows-1251")]
+    Windows1251, // Кириллица
+    #[serde(rename = "windows-1252")]
+    Windows1252, // Latin-1 и западноевропейские
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +53,32 @@ pub struct AppStats {
     pub files: usize,
     pub size: u64,
     pub tokens: usize,
+    // Токены без удаления комментариев, нумерации строк и т.п.; только в get_stats
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_tokens: Option<usize>,
+}
+
+// Экономия токенов от вывода скелета файла
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkeletonSavings {
+    pub path: String,
+    pub tokens: usize,      // Токены скелета
+    pub full_tokens: usize, // Токены полного текста
+}
+
+// Файл, скрытый правилами приватности
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivateFile {
+    pub path: String,
+    pub rule: String, // Совпавший шаблон из private_patterns
+}
+
+// Секрет, замененный в выводе на [REDACTED:kind]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Redaction {
+    pub path: String,
+    pub line: usize, // С 1; для раздела "Changes" - строка diff
+    pub kind: String, // Вид секрета: "aws-key", "private-key", "custom", "high-entropy"...
 }
 
 // Структура для обновления с фронтенда
@@ -32,6 +87,13 @@ pub struct FileUpdate {
     pub id: String,
     pub size: u64,
     pub token_count: usize,
+    #[serde(default)]
+    pub class: Option<FileClass>,
+    #[serde(default)]
+    pub encoding: Option<TextEncoding>,
+    // Some(false) - выбор снят, т.к. файл сгенерирован (deselect_generated)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selected: Option<bool>,
 }
 
 // Структура для события прогресса генерации
@@ -41,3 +103,17 @@ pub struct ProgressEvent {
     pub total: usize,
     pub stage: String, // "scanning", "processing", "writing"
 }
+
+// Структура для события инкрементального изменения дерева (tree-changed)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TreeDelta {
+    pub added: Vec<FileNode>,
+    pub removed: Vec<String>,  // ID удаленных узлов (включая вложенные)
+    pub modified: Vec<String>, // ID файлов, у которых изменилось содержимое
+}
+
+impl TreeDelta {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
@@ -16,6 +16,7 @@ pub enum SettingType {
     Textarea {
         rows: u8,
     },
+    Toggle,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,6 +58,54 @@ pub fn get_app_settings_schema() -> Vec<SettingSection> {
                         suffix: Some("tokens".to_string()),
                     },
                 },
+                SettingField {
+                    key: "auto_select_priorities".to_string(),
+                    label: "Fit Priorities".to_string(),
+                    description: Some("Order in which \"Fit to Token Limit\" picks files after pinned ones. shallow: closer to the repository root, small: fewer tokens, recent: recently modified.".to_string()),
+                    component: SettingType::Tags,
+                },
+                SettingField {
+                    key: "auto_select_pinned".to_string(),
+                    label: "Pinned Paths".to_string(),
+                    description: Some("Files and folders (relative paths) that \"Fit to Token Limit\" always takes first.".to_string()),
+                    component: SettingType::Tags,
+                },
+                SettingField {
+                    key: "auto_select_exclude_tests".to_string(),
+                    label: "Fit Without Tests".to_string(),
+                    description: Some("Skip test files and folders when fitting, unless pinned.".to_string()),
+                    component: SettingType::Toggle,
+                },
+                SettingField {
+                    key: "tokenizer".to_string(),
+                    label: "Tokenizer".to_string(),
+                    description: Some("cl100k: GPT-4, o200k: GPT-4o and newer, p50k: Codex, chars: characters per token estimate, huggingface: local tokenizer.json.".to_string()),
+                    component: SettingType::Select {
+                        options: vec![
+                            "cl100k".to_string(),
+                            "o200k".to_string(),
+                            "p50k".to_string(),
+                            "chars".to_string(),
+                            "huggingface".to_string(),
+                        ],
+                    },
+                },
+                SettingField {
+                    key: "tokenizer_path".to_string(),
+                    label: "Tokenizer File".to_string(),
+                    description: Some("Path to a HuggingFace tokenizer.json (used with \"huggingface\").".to_string()),
+                    component: SettingType::Text,
+                },
+                SettingField {
+                    key: "chars_per_token".to_string(),
+                    label: "Characters per Token".to_string(),
+                    description: Some("Used with the \"chars\" tokenizer.".to_string()),
+                    component: SettingType::Number {
+                        min: Some(1),
+                        max: Some(20),
+                        suffix: Some("chars".to_string()),
+                    },
+                },
             ],
         },
         SettingSection {
@@ -79,14 +128,170 @@ pub fn get_app_settings_schema() -> Vec<SettingSection> {
                         suffix: Some("bytes".to_string()),
                     },
                 },
+                SettingField {
+                    key: "output_format".to_string(),
+                    label: "Output Format".to_string(),
+                    description: Some("markdown: header + Output Template per file. xml: <documents> with the file tree and one <document> per file. json: one object with the tree and a files array. jsonl: one file record per line. html: standalone page with a collapsible tree and a section per file.".to_string()),
+                    component: SettingType::Select {
+                        options: vec![
+                            "markdown".to_string(),
+                            "xml".to_string(),
+                            "json".to_string(),
+                            "jsonl".to_string(),
+                            "html".to_string(),
+                        ],
+                    },
+                },
+                SettingField {
+                    key: "split_output".to_string(),
+                    label: "Split Output".to_string(),
+                    description: Some("Write output-part-1.md, output-part-2.md, ... each within the token budget.".to_string()),
+                    component: SettingType::Toggle,
+                },
+                SettingField {
+                    key: "split_token_budget".to_string(),
+                    label: "Tokens per Part".to_string(),
+                    description: Some("Files are only split when a single file exceeds this budget.".to_string()),
+                    component: SettingType::Number {
+                        min: Some(1000),
+                        max: None,
+                        suffix: Some("tokens".to_string()),
+                    },
+                },
+                SettingField {
+                    key: "line_numbers".to_string(),
+                    label: "Line Numbers".to_string(),
+                    description: Some("Prefix every line of file content with its line number. Token counts include the numbering.".to_string()),
+                    component: SettingType::Toggle,
+                },
+                SettingField {
+                    key: "line_number_width".to_string(),
+                    label: "Line Number Width".to_string(),
+                    description: Some("Right-aligned width of the number. 0 fits the last line number of each file.".to_string()),
+                    component: SettingType::Number {
+                        min: Some(0),
+                        max: Some(10),
+                        suffix: Some("chars".to_string()),
+                    },
+                },
+                SettingField {
+                    key: "line_number_separator".to_string(),
+                    label: "Line Number Separator".to_string(),
+                    description: Some("Text between the number and the line.".to_string()),
+                    component: SettingType::Text,
+                },
+                SettingField {
+                    key: "strip_comments".to_string(),
+                    label: "Strip Comments".to_string(),
+                    description: Some("Remove comments (including doc comments) from source files. Files that cannot be parsed safely are kept as is.".to_string()),
+                    component: SettingType::Toggle,
+                },
+                SettingField {
+                    key: "collapse_blank_lines".to_string(),
+                    label: "Collapse Blank Lines".to_string(),
+                    description: Some("Replace runs of blank lines with a single one.".to_string()),
+                    component: SettingType::Toggle,
+                },
+                SettingField {
+                    key: "trim_trailing_whitespace".to_string(),
+                    label: "Trim Trailing Whitespace".to_string(),
+                    description: Some("Remove spaces and tabs at line ends, outside of string literals.".to_string()),
+                    component: SettingType::Toggle,
+                },
+                SettingField {
+                    key: "strip_indentation".to_string(),
+                    label: "Strip Indentation".to_string(),
+                    description: Some("Remove leading indentation. Never applied to Python and shell.".to_string()),
+                    component: SettingType::Toggle,
+                },
+                SettingField {
+                    key: "skeleton_mode".to_string(),
+                    label: "Skeleton Mode".to_string(),
+                    description: Some("Generate only signatures and doc comments of every Rust, TypeScript/JavaScript, Python and Go file; function bodies become a marker. Single files and folders can be switched with the { } button in the tree.".to_string()),
+                    component: SettingType::Toggle,
+                },
+                SettingField {
+                    key: "redact_secrets".to_string(),
+                    label: "Redact Secrets".to_string(),
+                    description: Some("Replace API keys, tokens, private keys, passwords and other secrets with [REDACTED:kind] in the output. The result lists every redaction. Off by default.".to_string()),
+                    component: SettingType::Toggle,
+                },
+                SettingField {
+                    key: "secret_patterns".to_string(),
+                    label: "Secret Patterns".to_string(),
+                    description: Some("Extra regular expressions to redact. If a pattern has a capture group, only the group is replaced.".to_string()),
+                    component: SettingType::Tags,
+                },
+                SettingField {
+                    key: "secret_entropy_threshold".to_string(),
+                    label: "Entropy Threshold".to_string(),
+                    description: Some("Redact random-looking strings of 20+ characters at or above this entropy (bits per character). 0 turns the check off.".to_string()),
+                    component: SettingType::Number {
+                        min: Some(0),
+                        max: Some(8),
+                        suffix: Some("bits".to_string()),
+                    },
+                },
+                SettingField {
+                    key: "strip_languages".to_string(),
+                    label: "Strip Languages".to_string(),
+                    description: Some("Languages the options above apply to. Supported: rust, typescript, tsx, javascript, jsx, python, go, c, cpp, java, bash.".to_string()),
+                    component: SettingType::Tags,
+                },
                 SettingField {
                     key: "output_template".to_string(),
                     label: "Output Template".to_string(),
-                    description: Some("Variables: {{path}}, {{language}}, {{content}}".to_string()),
+                    description: Some("Markdown format only. Jinja2 syntax ({% if %}, {% for %}, filters). Variables: {{path}}, {{name}}, {{extension}}, {{absolute_path}}, {{language}}, {{content}}, {{fence}}, {{size}}, {{tokens}}, {{lines}}, {{modified}}, {{index}}, {{total}}, {{git_commit}}, {{git_author}}, {{git_date}}, {{git_subject}}, {{git_commits}}".to_string()),
+                    component: SettingType::Textarea { rows: 6 },
+                },
+                SettingField {
+                    key: "header_template".to_string(),
+                    label: "Header Template".to_string(),
+                    description: Some("Markdown format only. Variables: {{tree}} (none when a part lists its files instead), {{files}}, {{part}} (none unless split; part.index, part.total)".to_string()),
+                    component: SettingType::Textarea { rows: 6 },
+                },
+                SettingField {
+                    key: "footer_template".to_string(),
+                    label: "Footer Template".to_string(),
+                    description: Some("Markdown format only. Appended after the files. Same variables as the header.".to_string()),
+                    component: SettingType::Textarea { rows: 3 },
+                },
+                SettingField {
+                    key: "template_partials".to_string(),
+                    label: "Template Partials".to_string(),
+                    description: Some("Reusable {% macro name(args) %}...{% endmacro %} definitions, called from any template as {{ partials.name(...) }}".to_string()),
                     component: SettingType::Textarea { rows: 6 },
                 },
             ],
         },
+        SettingSection {
+            id: "git".to_string(),
+            label: "Git".to_string(),
+            fields: vec![
+                SettingField {
+                    key: "include_git_diff".to_string(),
+                    label: "Include Changes".to_string(),
+                    description: Some("Append a \"Changes\" section with unified diffs of the selected files.".to_string()),
+                    component: SettingType::Toggle,
+                },
+                SettingField {
+                    key: "git_diff_base".to_string(),
+                    label: "Diff Against".to_string(),
+                    description: Some("Branch, tag or commit. Leave empty to diff against the index.".to_string()),
+                    component: SettingType::Text,
+                },
+                SettingField {
+                    key: "git_diff_context".to_string(),
+                    label: "Context Lines".to_string(),
+                    description: Some("Unchanged lines shown around each change.".to_string()),
+                    component: SettingType::Number {
+                        min: Some(0),
+                        max: Some(100),
+                        suffix: Some("lines".to_string()),
+                    },
+                },
+            ],
+        },
         SettingSection {
             id: "filters".to_string(),
             label: "Filters".to_string(),
@@ -103,12 +308,42 @@ pub fn get_app_settings_schema() -> Vec<SettingSection> {
                     description: Some("Exact match for folder names to skip.".to_string()),
                     component: SettingType::Tags,
                 },
+                SettingField {
+                    key: "ignore_patterns".to_string(),
+                    label: "Ignore Patterns".to_string(),
+                    description: Some("Gitignore-style patterns relative to the project root, e.g. *.generated.ts or docs/**/*.png. Prefix with ! to keep a match.".to_string()),
+                    component: SettingType::Tags,
+                },
+                SettingField {
+                    key: "include_patterns".to_string(),
+                    label: "Include Only".to_string(),
+                    description: Some("If set, only matching files are listed, e.g. src/** and Cargo.toml. Matches are kept even if ignored; binary and private files are still skipped.".to_string()),
+                    component: SettingType::Tags,
+                },
                 SettingField {
                     key: "binary_extensions".to_string(),
                     label: "Binary Extensions".to_string(),
                     description: Some("Files with these extensions will be skipped.".to_string()),
                     component: SettingType::Tags,
                 },
+                SettingField {
+                    key: "private_patterns".to_string(),
+                    label: "Private Files".to_string(),
+                    description: Some("Gitignore-style patterns for keys and credentials that are never listed, even with Include Only. Case-insensitive.".to_string()),
+                    component: SettingType::Tags,
+                },
+                SettingField {
+                    key: "private_allow".to_string(),
+                    label: "Allowed Private Files".to_string(),
+                    description: Some("Exceptions to Private Files, e.g. .env.example, keyboard.key or **/secrets_test.rs.".to_string()),
+                    component: SettingType::Tags,
+                },
+                SettingField {
+                    key: "deselect_generated".to_string(),
+                    label: "Deselect Generated Files".to_string(),
+                    description: Some("Leave new generated and minified files (Code generated ... DO NOT EDIT, @generated or <auto-generated> banners, *.min.js, *.pb.go, very long lines) unselected.".to_string()),
+                    component: SettingType::Toggle,
+                },
             ],
         },
     ]
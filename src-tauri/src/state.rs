@@ -1,24 +1,116 @@
-use crate::models::FileNode;
-use std::collections::HashMap;
-use std::sync::Mutex;
+use crate::engine::{ContentTransform, RepoWatcher, Selection, TokenCache, Tokenizer, TokenizerKind};
+use crate::models::AppConfig;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
 
 pub struct AppState {
     // Храним плоский список узлов для быстрого доступа O(1)
-    pub nodes: Mutex<HashMap<String, FileNode>>,
+    pub nodes: Mutex<Selection>,
     pub root_path: Mutex<Option<String>>,
-    // Кэш для последнего сгенерированного контента
-    pub last_generated_content: Mutex<Option<String>>,
+    // Кэш для последнего сгенерированного контента (при разбиении - первая часть) и число частей
+    pub last_generated_content: Mutex<Option<(String, usize)>>,
     // ID текущего сканирования для предотвращения гонок
     pub current_scan_id: Mutex<u32>,
+    // Наблюдатель за изменениями ФС (включается отдельно через start_watching)
+    pub watcher: Mutex<Option<RepoWatcher>>,
+    // Токенизатор текущих настроек (загружается при открытии проекта)
+    pub tokenizer: Mutex<Option<Arc<Tokenizer>>>,
+    // Преобразования содержимого перед подсчетом токенов (нумерация строк)
+    pub transform: Mutex<ContentTransform>,
+    // Кэш токенов открытого проекта для текущего токенизатора
+    pub token_cache: Mutex<Option<Arc<TokenCache>>>,
 }
 
 impl AppState {
     pub fn new() -> Self {
         Self {
-            nodes: Mutex::new(HashMap::new()),
+            nodes: Mutex::new(Selection::new()),
             root_path: Mutex::new(None),
             last_generated_content: Mutex::new(None),
             current_scan_id: Mutex::new(0),
+            watcher: Mutex::new(None),
+            tokenizer: Mutex::new(None),
+            transform: Mutex::new(ContentTransform::default()),
+            token_cache: Mutex::new(None),
         }
     }
+
+    // Токенизатор для настроек из конфига. Перезагружается только при смене настроек;
+    // в этом случае токены, посчитанные старым токенизатором, сбрасываются.
+    pub fn tokenizer_for(&self, config: &AppConfig) -> Result<Arc<Tokenizer>, String> {
+        let kind = TokenizerKind::from_config(config)?;
+        let mut current = self.tokenizer.lock().map_err(|_| "Failed to lock tokenizer")?;
+        if let Some(tokenizer) = current.as_ref().filter(|t| t.cache_key() == kind.cache_key()) {
+            return Ok(tokenizer.clone());
+        }
+
+        let tokenizer = Tokenizer::shared(kind)?;
+        let changed = current.replace(tokenizer.clone()).is_some();
+        drop(current);
+
+        if changed {
+            self.reset_token_counts();
+        }
+        Ok(tokenizer)
+    }
+
+    // Преобразования содержимого из конфига; при их смене токены тоже пересчитываются
+    pub fn transform_for(&self, config: &AppConfig) -> Result<ContentTransform, String> {
+        let transform = ContentTransform::from_config(config);
+        let mut current = self.transform.lock().map_err(|_| "Failed to lock transform")?;
+        if *current != transform {
+            *current = transform.clone();
+            drop(current);
+            self.reset_token_counts();
+        }
+        Ok(transform)
+    }
+
+    pub fn current_transform(&self) -> Result<ContentTransform, String> {
+        Ok(self.transform.lock().map_err(|_| "Failed to lock transform")?.clone())
+    }
+
+    // Токены, посчитанные по старым настройкам, больше не верны
+    fn reset_token_counts(&self) {
+        if let Ok(mut cache) = self.token_cache.lock() {
+            cache.take();
+        }
+        if let Ok(mut selection) = self.nodes.lock() {
+            for node in selection.nodes_mut().values_mut() {
+                node.token_count = None;
+            }
+        }
+    }
+
+    // Текущий токенизатор (cl100k по умолчанию, если проект еще не открывали)
+    pub fn current_tokenizer(&self) -> Result<Arc<Tokenizer>, String> {
+        let current = self
+            .tokenizer
+            .lock()
+            .map_err(|_| "Failed to lock tokenizer")?
+            .clone();
+        match current {
+            Some(tokenizer) => Ok(tokenizer),
+            None => self.tokenizer_for(&AppConfig::default()),
+        }
+    }
+
+    // Кэш токенов проекта: загружается с диска при первом обращении после открытия проекта
+    // или смены токенизатора (преобразований)
+    pub fn token_cache_for(
+        &self,
+        root: &Path,
+        tokenizer: &Tokenizer,
+        transform: &ContentTransform,
+    ) -> Result<Arc<TokenCache>, String> {
+        let mut cache = self.token_cache.lock().map_err(|_| "Failed to lock token cache")?;
+        let cache = cache.get_or_insert_with(|| Arc::new(TokenCache::open(root, tokenizer, transform)));
+        Ok(cache.clone())
+    }
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
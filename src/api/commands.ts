@@ -13,12 +13,28 @@ export interface FileNode {
   token_count: number | null;
   selected: boolean;
   expanded: boolean;
+  skeleton: boolean; // Только сигнатуры, тела функций скрыты
+  class?: FileClass | null; // Определяется фоновым анализом
+  encoding?: TextEncoding | null; // Исходная кодировка (текст всегда отдается в UTF-8)
 }
 
+export type FileClass = 'generated' | 'minified';
+
+export type TextEncoding = 'utf-8' | 'utf-16le' | 'utf-16be' | 'windows-1251' | 'windows-1252';
+
 export interface FileUpdate {
   id: string;
   size: number;
   token_count: number;
+  class: FileClass | null;
+  encoding: TextEncoding | null; // null - бинарный файл
+  selected?: boolean; // false - выбор снят, т.к. файл сгенерирован
+}
+
+export interface TreeDelta {
+  added: FileNode[];
+  removed: string[]; // ID удаленных узлов (включая вложенные)
+  modified: string[]; // ID файлов с измененным содержимым
 }
 
 export interface ProgressEvent {
@@ -31,12 +47,60 @@ export interface AppStats {
   files: number;
   size: number;
   tokens: number;
+  raw_tokens?: number; // Без удаления комментариев, нумерации строк и т.п. (только get_stats)
+}
+
+export interface OutputPart {
+  path: string | null; // null, если вывод не записывался в файл
+  stats: AppStats; // tokens - фактический размер части вместе с заголовком
 }
 
 export interface GenerateResult {
   preview_content: string;
   is_truncated: boolean;
   stats: AppStats;
+  parts: OutputPart[]; // пусто, если разбиение выключено
+  skeletons: SkeletonSavings[]; // Файлы, выведенные скелетом
+  redactions: Redaction[]; // Замененные секреты - для проверки перед отправкой
+}
+
+export interface Redaction {
+  path: string; // Для раздела "Changes" - "Changes (git diff)"
+  line: number; // С 1
+  kind: string; // "aws-key", "private-key", "custom", "high-entropy"...
+}
+
+export interface SkeletonSavings {
+  path: string;
+  tokens: number; // Токены скелета
+  full_tokens: number; // Токены полного текста
+}
+
+export interface PrivateFile {
+  path: string;
+  rule: string; // Совпавший шаблон из private_patterns
+}
+
+export type AutoSelectPriority = 'shallow' | 'small' | 'recent';
+
+export interface AutoSelectOptions {
+  budget?: number; // По умолчанию token_limit
+  pinned?: string[];
+  priorities?: AutoSelectPriority[];
+  exclude_tests?: boolean;
+}
+
+export interface DroppedFile {
+  path: string;
+  tokens: number;
+  reason: 'over_budget' | 'test_file' | 'too_large';
+}
+
+export interface AutoSelection {
+  selected: string[]; // id выбранных файлов
+  tokens: number;
+  budget: number;
+  dropped: DroppedFile[];
 }
 
 // Параметры для команд
@@ -44,11 +108,43 @@ export interface AppConfig {
   ignored_names: string[];
   ignored_folders: string[];
   binary_extensions: string[];
+  ignore_patterns?: string[]; // Шаблоны в синтаксисе .gitignore
+  include_patterns?: string[]; // Если заданы - только совпавшие файлы
+  private_patterns?: string[]; // Приватные файлы, скрываются всегда
+  private_allow?: string[]; // Исключения из private_patterns
+  deselect_generated?: boolean; // Новые сгенерированные и минифицированные файлы не выбираются
   token_limit?: number; // Опционально для обратной совместимости
   max_file_size?: number;
-  output_template?: string;
+  auto_select_pinned?: string[]; // Пути, которые автовыбор берет первыми
+  auto_select_priorities?: AutoSelectPriority[];
+  auto_select_exclude_tests?: boolean;
+  output_format?: 'markdown' | 'xml' | 'json' | 'jsonl' | 'html';
+  output_template?: string; // Шаблон файла (Jinja2)
+  header_template?: string;
+  footer_template?: string;
+  template_partials?: string; // Макросы, доступные в шаблонах как `partials`
+  line_numbers?: boolean;
+  line_number_width?: number; // 0 - по номеру последней строки
+  line_number_separator?: string;
+  strip_comments?: boolean;
+  collapse_blank_lines?: boolean;
+  trim_trailing_whitespace?: boolean;
+  strip_indentation?: boolean;
+  strip_languages?: string[]; // Языки, к которым применяются strip_* и collapse_blank_lines
+  skeleton_mode?: boolean; // Все файлы как скелет (иначе только отмеченные в дереве)
+  redact_secrets?: boolean; // Заменять секреты на [REDACTED:вид]
+  secret_patterns?: string[]; // Дополнительные регулярные выражения
+  secret_entropy_threshold?: number; // Бит на символ; 0 - без проверки энтропии
   theme?: 'system' | 'light' | 'dark';
   output_filename?: string;
+  include_git_diff?: boolean;
+  git_diff_base?: string; // пусто - сравнение с индексом
+  git_diff_context?: number;
+  split_output?: boolean;
+  split_token_budget?: number;
+  tokenizer?: 'cl100k' | 'o200k' | 'p50k' | 'chars' | 'huggingface';
+  tokenizer_path?: string; // tokenizer.json для 'huggingface'
+  chars_per_token?: number; // для 'chars'
 }
 
 export type SettingType = 
@@ -56,7 +152,8 @@ export type SettingType =
   | { type: 'Number'; options: { min?: number; max?: number; suffix?: string } }
   | { type: 'Select'; options: { options: string[] } }
   | { type: 'Tags' }
-  | { type: 'Textarea'; options: { rows: number } };
+  | { type: 'Textarea'; options: { rows: number } }
+  | { type: 'Toggle' };
 
 export interface SettingField {
   key: string;
@@ -81,6 +178,11 @@ export interface UpdateSelectionParams {
   selected: boolean;
 }
 
+export interface UpdateSkeletonParams {
+  id: string;
+  skeleton: boolean;
+}
+
 export interface ToggleExpandedParams {
   id: string;
   expanded: boolean;
@@ -91,6 +193,18 @@ export interface ScanDirectoryParams {
   config?: AppConfig;
 }
 
+export type DiffTarget = 'working_tree' | 'head';
+
+export interface SelectChangedFilesParams {
+  baseRef: string; // ветка, тег или хэш коммита
+  target?: DiffTarget; // по умолчанию рабочая копия
+  mergeBase?: boolean; // сравнивать с общим предком (как `git diff ref...`)
+}
+
+export interface StartWatchingParams {
+  config?: AppConfig;
+}
+
 export interface GenerateMarkdownParams {
   outputPath?: string;
   config?: AppConfig;
@@ -100,6 +214,25 @@ export interface SearchNodesParams {
   query: string;
 }
 
+export interface ContentQuery {
+  pattern: string;
+  regex?: boolean; // false - поиск подстроки
+  case_sensitive?: boolean;
+}
+
+export interface ContentMatch {
+  id: string;
+  path: string;
+  line: number; // С 1
+  snippet: string;
+}
+
+export interface ContentSearch {
+  files: string[]; // id всех файлов с совпадениями
+  matches: ContentMatch[];
+  truncated: boolean; // matches обрезаны (до 1000)
+}
+
 export interface ReadFileParams {
   id: string;
 }
@@ -127,6 +260,10 @@ export const commands = {
     return invoke('update_selection', { id: params.id, selected: params.selected });
   },
 
+  updateSkeleton: (params: UpdateSkeletonParams): Promise<void> => {
+    return invoke('update_skeleton', { id: params.id, skeleton: params.skeleton });
+  },
+
   toggleExpanded: (params: ToggleExpandedParams): Promise<void> => {
     return invoke('toggle_expanded', { id: params.id, expanded: params.expanded });
   },
@@ -147,6 +284,34 @@ export const commands = {
     return invoke<string[]>('search_nodes', { query: params.query });
   },
 
+  // Поиск по содержимому файлов (config - для max_file_size)
+  searchContent: (query: ContentQuery, config: AppConfig): Promise<ContentSearch> => {
+    return invoke<ContentSearch>('search_content', { query, config });
+  },
+
+  // Возвращает id изменившихся узлов (при выборе - вместе с папками)
+  selectByContent: (query: ContentQuery, selected: boolean, config: AppConfig): Promise<string[]> => {
+    return invoke<string[]>('select_by_content', { query, selected, config });
+  },
+
+  // Git commands
+  selectChangedFiles: (params: SelectChangedFilesParams): Promise<string[]> => {
+    return invoke<string[]>('select_changed_files', {
+      baseRef: params.baseRef,
+      target: params.target,
+      mergeBase: params.mergeBase,
+    });
+  },
+
+  // Watcher commands
+  startWatching: (params: StartWatchingParams = {}): Promise<void> => {
+    return invoke('start_watching', { config: params.config });
+  },
+
+  stopWatching: (): Promise<void> => {
+    return invoke('stop_watching');
+  },
+
   // Generator commands
   generateMarkdown: (params: GenerateMarkdownParams): Promise<GenerateResult> => {
     // Tauri автоматически конвертирует camelCase -> snake_case для Rust
@@ -174,8 +339,9 @@ export const commands = {
   },
 
   // Clipboard command
-  copyToClipboard: (): Promise<void> => {
-    return invoke('copy_from_cache_to_clipboard');
+  // Число частей разбитого вывода (скопирована первая), 0 - скопирован весь документ
+  copyToClipboard: (): Promise<number> => {
+    return invoke<number>('copy_from_cache_to_clipboard');
   },
 
   // Selection commands
@@ -187,6 +353,16 @@ export const commands = {
     return invoke('deselect_all');
   },
 
+  // Без options параметры берутся из config (auto_select_*)
+  autoSelect: (config: AppConfig, options?: AutoSelectOptions): Promise<AutoSelection> => {
+    return invoke<AutoSelection>('auto_select', { options, config });
+  },
+
+  // Файлы, скрытые правилами приватности
+  listPrivateFiles: (config: AppConfig): Promise<PrivateFile[]> => {
+    return invoke<PrivateFile[]>('list_private_files', { config });
+  },
+
   // File system operations
   revealInExplorer: async (path: string): Promise<void> => {
     try {
@@ -205,5 +381,10 @@ export const commands = {
   getDefaultConfig: (): Promise<AppConfig> => {
     return invoke('get_default_config');
   },
+
+  // Ошибка (строка) описывает первый неверный шаблон
+  validateConfig: (config: AppConfig): Promise<void> => {
+    return invoke('validate_config', { config });
+  },
 };
 
@@ -2,7 +2,7 @@ import { defineStore } from 'pinia';
 import { open } from '@tauri-apps/plugin-dialog';
 import { listen } from '@tauri-apps/api/event';
 import { LazyStore } from '@tauri-apps/plugin-store';
-import { commands, type FileNode, type AppStats, type GenerateResult, type FileUpdate, type AppConfig } from '../api/commands';
+import { commands, type FileNode, type AppStats, type GenerateResult, type FileUpdate, type TreeDelta, type AppConfig, type AutoSelectOptions, type AutoSelection, type ContentMatch, type ContentQuery } from '../api/commands';
 
 // Re-export типы для обратной совместимости
 export type { FileNode, AppStats, GenerateResult, AppConfig };
@@ -10,6 +10,9 @@ export type { FileNode, AppStats, GenerateResult, AppConfig };
 // Инициализация стора (settings.json создастся в AppData)
 const settingsStore = new LazyStore('settings.json');
 
+// Шаблон по умолчанию до появления {{fence}} (ломался на файлах с ```)
+const LEGACY_OUTPUT_TEMPLATE = "## {{path}}\n\n```{{language}}\n{{content}}\n```\n\n---\n\n";
+
 export interface FlattenedNode extends FileNode {
   depth: number;
 }
@@ -31,20 +34,70 @@ export const useRepoStore = defineStore('repo', {
     loadingNodes: new Set<string>(), // Узлы, которые сейчас загружаются
     searchQuery: '', // Поисковый запрос
     searchResults: [] as string[], // ID найденных узлов
+    searchMode: 'name' as 'name' | 'content', // Поиск по имени или по содержимому файлов
+    searchRegex: false, // Запрос по содержимому - регулярное выражение
+    searchError: null as string | null, // Неверное регулярное выражение и т.п.
+    contentMatches: [] as ContentMatch[], // Совпадения поиска по содержимому
     focusedNodeId: null as string | null, // ID сфокусированного узла для клавиатурной навигации
     config: {
       ignored_names: [],
       ignored_folders: [],
       binary_extensions: [],
+      ignore_patterns: [],
+      include_patterns: [],
+      private_patterns: ['.env', '.env.*', '*.secret', '*.key', '*.pem', '*id_rsa*', '*secrets*'],
+      private_allow: ['.env.example', '.env.sample', '.env.template'],
+      deselect_generated: true,
       token_limit: 128000,
       max_file_size: 1024 * 1024,
-      output_template: "## {{path}}\n\n```{{language}}\n{{content}}\n```\n\n---\n\n",
+      auto_select_pinned: [],
+      auto_select_priorities: ['shallow', 'small'],
+      auto_select_exclude_tests: true,
+      output_format: 'markdown',
+      output_template: "## {{path}}\n\n{{fence}}{{language}}\n{{content}}\n{{fence}}\n\n---\n\n",
+      header_template: "# Collected Files{% if part %} (part {{ part.index }} of {{ part.total }}){% endif %}\n\n{% if tree is not none %}## File Structure\n\n```\n{{ tree }}\n```\n{% else %}## Files in This Part\n\n{% for file in files %}- {{ file }}\n{% endfor %}{% endif %}\n---\n\n",
+      footer_template: '',
+      template_partials: '',
+      line_numbers: false,
+      line_number_width: 0,
+      line_number_separator: ' | ',
+      strip_comments: false,
+      collapse_blank_lines: false,
+      trim_trailing_whitespace: false,
+      strip_indentation: false,
+      strip_languages: ['rust', 'typescript', 'tsx', 'javascript', 'jsx', 'python', 'go', 'c', 'cpp', 'java', 'bash'],
+      skeleton_mode: false,
+      redact_secrets: false,
+      secret_patterns: [],
+      secret_entropy_threshold: 4.5,
       theme: 'system',
       output_filename: 'output.md',
+      include_git_diff: false,
+      git_diff_base: '',
+      git_diff_context: 3,
+      split_output: false,
+      split_token_budget: 100000,
+      tokenizer: 'cl100k',
+      tokenizer_path: '',
+      chars_per_token: 4,
     } as AppConfig,
   }),
 
   getters: {
+    // Совпадения поиска по содержимому по id файла
+    contentMatchesById(state): Map<string, ContentMatch[]> {
+      const map = new Map<string, ContentMatch[]>();
+      for (const match of state.contentMatches) {
+        const list = map.get(match.id);
+        if (list) {
+          list.push(match);
+        } else {
+          map.set(match.id, [match]);
+        }
+      }
+      return map;
+    },
+
     // Плоский список видимых узлов для виртуализации (старый, для совместимости)
     visibleNodes(): FlattenedNode[] {
       return this.visibleRows;
@@ -144,9 +197,14 @@ export const useRepoStore = defineStore('repo', {
           if (savedConfig.max_file_size === undefined) {
             savedConfig.max_file_size = defaultConfig.max_file_size;
           }
-          if (!savedConfig.output_template) {
+          // Старый шаблон по умолчанию с фиксированной ``` заменяем новым ({{fence}})
+          if (!savedConfig.output_template || savedConfig.output_template === LEGACY_OUTPUT_TEMPLATE) {
             savedConfig.output_template = defaultConfig.output_template;
           }
+          // Пустой заголовок допустим, поэтому подставляем только отсутствующий
+          if (savedConfig.header_template === undefined) {
+            savedConfig.header_template = defaultConfig.header_template;
+          }
           if (!savedConfig.theme) {
             savedConfig.theme = defaultConfig.theme;
           }
@@ -179,7 +237,7 @@ export const useRepoStore = defineStore('repo', {
             binary_extensions: [],
             token_limit: 128000,
             max_file_size: 1024 * 1024,
-            output_template: "## {{path}}\n\n```{{language}}\n{{content}}\n```\n\n---\n\n",
+            output_template: "## {{path}}\n\n{{fence}}{{language}}\n{{content}}\n{{fence}}\n\n---\n\n",
             theme: 'system',
             output_filename: 'output.md',
           };
@@ -189,6 +247,9 @@ export const useRepoStore = defineStore('repo', {
     },
 
     async saveSettings(newConfig: AppConfig) {
+      // Ошибку в шаблонах показываем пользователю, настройки не сохраняем
+      await commands.validateConfig(newConfig);
+
       try {
         this.config = newConfig;
         await settingsStore.set('app_config', newConfig);
@@ -285,6 +346,11 @@ export const useRepoStore = defineStore('repo', {
           if (update) {
             node.size = update.size;
             node.token_count = update.token_count;
+            node.class = update.class;
+            node.encoding = update.encoding;
+            if (update.selected !== undefined) {
+              node.selected = update.selected;
+            }
           }
         }
         
@@ -292,6 +358,23 @@ export const useRepoStore = defineStore('repo', {
         this.recalculateStats();
       });
 
+      // Инкрементальные изменения дерева от наблюдателя ФС (start_watching)
+      await listen<TreeDelta>('tree-changed', (event) => {
+        const { added, removed, modified } = event.payload;
+        const removedSet = new Set(removed);
+        const modifiedSet = new Set(modified);
+
+        const kept = this.nodes.filter(n => !removedSet.has(n.id));
+        for (const node of kept) {
+          if (modifiedSet.has(node.id)) {
+            node.size = null;
+            node.token_count = null;
+          }
+        }
+        this.nodes = kept.concat(added);
+        this.recalculateStats();
+      });
+
       await listen('analysis-completed', () => {
         this.isAnalyzing = false;
         this.recalculateStats();
@@ -309,6 +392,21 @@ export const useRepoStore = defineStore('repo', {
         }
       }
       this.stats = { files, size, tokens };
+
+      // Исходное количество токенов (до удаления комментариев и т.п.) знает только бэкенд
+      const c = this.config;
+      if (c.line_numbers || c.strip_comments || c.collapse_blank_lines || c.trim_trailing_whitespace || c.strip_indentation) {
+        this.loadRawTokens();
+      }
+    },
+
+    async loadRawTokens() {
+      try {
+        const stats = await commands.getStats();
+        this.stats = { ...this.stats, raw_tokens: stats.raw_tokens };
+      } catch (error) {
+        console.error('Failed to load stats:', error);
+      }
     },
 
     async loadCurrentDirectory() {
@@ -370,6 +468,22 @@ export const useRepoStore = defineStore('repo', {
       this.recalculateStats();
     },
 
+    // Режим скелета (только сигнатуры); для папки - для всего содержимого
+    async toggleSkeleton(nodeId: string) {
+      const node = this.nodes.find(n => n.id === nodeId);
+      if (!node) return;
+
+      const skeleton = !node.skeleton;
+      const prefix = `${nodeId}/`;
+      for (const n of this.nodes) {
+        if (n.id === nodeId || (node.is_directory && n.id.startsWith(prefix))) {
+          n.skeleton = skeleton;
+        }
+      }
+
+      await commands.updateSkeleton({ id: nodeId, skeleton });
+    },
+
     async toggleExpanded(nodeId: string) {
       const node = this.nodes.find(n => n.id === nodeId);
       if (node && node.is_directory) {
@@ -410,11 +524,20 @@ export const useRepoStore = defineStore('repo', {
 
     async searchNodes(query: string) {
       this.searchQuery = query;
+      this.searchError = null;
+      this.contentMatches = [];
       if (query.trim()) {
         try {
-          this.searchResults = await commands.searchNodes({ query });
+          if (this.searchMode === 'content') {
+            const result = await commands.searchContent(this.contentQuery(), this.config);
+            this.searchResults = result.files;
+            this.contentMatches = result.matches;
+          } else {
+            this.searchResults = await commands.searchNodes({ query });
+          }
         } catch (error) {
           console.error('Search failed:', error);
+          this.searchError = String(error);
           this.searchResults = [];
         }
       } else {
@@ -422,6 +545,22 @@ export const useRepoStore = defineStore('repo', {
       }
     },
 
+    contentQuery(): ContentQuery {
+      return { pattern: this.searchQuery.trim(), regex: this.searchRegex };
+    },
+
+    // Выбрать или снять выбор со всех файлов, совпавших с поиском по содержимому
+    async selectByContent(selected: boolean) {
+      if (this.searchMode !== 'content' || !this.searchQuery.trim()) return;
+      const changed = new Set(await commands.selectByContent(this.contentQuery(), selected, this.config));
+      for (const node of this.nodes) {
+        if (changed.has(node.id)) {
+          node.selected = selected;
+        }
+      }
+      this.recalculateStats();
+    },
+
     collapseAll() {
       // Сворачиваем все папки
       for (const node of this.nodes) {
@@ -456,6 +595,27 @@ export const useRepoStore = defineStore('repo', {
       await commands.deselectAll();
       this.recalculateStats();
     },
+
+    // Подбор файлов под token_limit; бэкенд уже обновил выбор, повторяем его локально
+    async autoSelect(options?: AutoSelectOptions): Promise<AutoSelection> {
+      const result = await commands.autoSelect(this.config, options);
+      const selected = new Set(result.selected);
+      const nodeMap = new Map(this.nodes.map(n => [n.id, n]));
+      // Папки выбираются, только если внутри есть выбранный файл
+      const selectedDirs = new Set<string>();
+      for (const id of result.selected) {
+        let parentId = nodeMap.get(id)?.parent_id;
+        while (parentId && !selectedDirs.has(parentId)) {
+          selectedDirs.add(parentId);
+          parentId = nodeMap.get(parentId)?.parent_id;
+        }
+      }
+      for (const node of this.nodes) {
+        node.selected = node.is_directory ? selectedDirs.has(node.id) : selected.has(node.id);
+      }
+      this.recalculateStats();
+      return result;
+    },
   },
 });
 